@@ -0,0 +1,133 @@
+//! 比较多个文件同时上传时 VideoStream 与阻塞读取的吞吐量，以及对运行时其他任务的影响
+//!
+//! cargo bench -p biliup --bench video_stream
+//! 文件大小可通过 BILIUP_BENCH_MB 调整，默认每个文件 64 MiB
+
+use biliup::uploader::VideoFile;
+use bytes::Bytes;
+use futures::TryStreamExt;
+use md5::{Digest, Md5};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+const CHUNK_SIZE: usize = 10485760;
+const CONCURRENCY: [usize; 3] = [1, 4, 8];
+const TICK: Duration = Duration::from_millis(1);
+
+fn main() -> std::io::Result<()> {
+    let size_mb: usize = std::env::var("BILIUP_BENCH_MB")
+        .ok()
+        .and_then(|mb| mb.parse().ok())
+        .unwrap_or(64);
+    let max_files = CONCURRENCY.into_iter().max().unwrap();
+    let files = (0..max_files)
+        .map(|i| {
+            let path = std::env::temp_dir().join(format!("biliup-bench-{i}.flv"));
+            std::fs::write(&path, vec![i as u8; size_mb * 1024 * 1024])?;
+            Ok(path)
+        })
+        .collect::<std::io::Result<Vec<_>>>()?;
+
+    // 工作线程较少时阻塞读取对其他任务的影响更明显
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_all()
+        .build()?;
+    println!(
+        "{:<12} {:>5} {:>12} {:>14}",
+        "reader", "files", "MB/s", "max tick lag"
+    );
+    for concurrency in CONCURRENCY {
+        let files = &files[..concurrency];
+        for (name, blocking) in [("blocking", true), ("VideoStream", false)] {
+            let (bytes, elapsed, lag) = rt.block_on(run(files, blocking))?;
+            println!(
+                "{name:<12} {concurrency:>5} {:>12.2} {:>12.2}ms",
+                bytes as f64 / 1048576. / elapsed.as_secs_f64(),
+                lag.as_secs_f64() * 1000.
+            );
+        }
+    }
+    for path in files {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// 同时读取所有文件，返回读取的字节数、耗时与计时任务的最大延迟
+async fn run(files: &[PathBuf], blocking: bool) -> std::io::Result<(u64, Duration, Duration)> {
+    let running = Arc::new(AtomicBool::new(true));
+    let max_lag = Arc::new(AtomicU64::new(0));
+    let ticker = tokio::spawn({
+        let running = running.clone();
+        let max_lag = max_lag.clone();
+        async move {
+            while running.load(Ordering::Relaxed) {
+                let instant = Instant::now();
+                tokio::time::sleep(TICK).await;
+                let lag = instant.elapsed().saturating_sub(TICK).as_micros() as u64;
+                max_lag.fetch_max(lag, Ordering::Relaxed);
+            }
+        }
+    });
+    let instant = Instant::now();
+    let tasks: Vec<_> = files
+        .iter()
+        .cloned()
+        .map(|path| {
+            tokio::spawn(async move {
+                if blocking {
+                    read_blocking(&path)
+                } else {
+                    read_stream(&path).await
+                }
+            })
+        })
+        .collect();
+    let mut bytes = 0;
+    for task in tasks {
+        bytes += task.await??;
+    }
+    let elapsed = instant.elapsed();
+    running.store(false, Ordering::Relaxed);
+    ticker.await?;
+    let lag = Duration::from_micros(max_lag.load(Ordering::Relaxed));
+    Ok((bytes, elapsed, lag))
+}
+
+/// 改用阻塞线程池之前的做法：直接在运行时线程中读取整个分块并计算 MD5
+fn read_blocking(path: &Path) -> std::io::Result<u64> {
+    let mut file = std::fs::File::open(path)?;
+    let mut buffer = vec![0u8; CHUNK_SIZE];
+    let mut file_md5 = Md5::new();
+    let mut bytes = 0;
+    loop {
+        let mut filled = 0;
+        while filled < CHUNK_SIZE {
+            match file.read(&mut buffer[filled..])? {
+                0 => break,
+                n => filled += n,
+            }
+        }
+        if filled == 0 {
+            return Ok(bytes);
+        }
+        let chunk = Bytes::copy_from_slice(&buffer[..filled]);
+        Md5::digest(&chunk);
+        file_md5.update(&chunk);
+        bytes += chunk.len() as u64;
+    }
+}
+
+async fn read_stream(path: &Path) -> std::io::Result<u64> {
+    VideoFile::new(path)?
+        .get_stream(CHUNK_SIZE)?
+        .try_fold(
+            0,
+            |bytes, chunk| async move { Ok(bytes + chunk.len() as u64) },
+        )
+        .await
+}
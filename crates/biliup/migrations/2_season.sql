@@ -0,0 +1,2 @@
+alter table upload_streamers
+    add column season_id INTEGER;
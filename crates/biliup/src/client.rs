@@ -1,10 +1,9 @@
-use crate::retry;
+use crate::retry_policy::RetryPolicy;
 use rand::Rng;
 use reqwest::header::HeaderMap;
-use reqwest::{header, Response};
+use reqwest::{header, ClientBuilder as HttpClientBuilder, Proxy, Response};
 use reqwest_cookie_store::CookieStoreMutex;
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
-use reqwest_retry::policies::ExponentialBackoff;
 use reqwest_retry::RetryTransientMiddleware;
 use std::sync::Arc;
 use std::time::Duration;
@@ -14,45 +13,54 @@ pub struct StatelessClient {
     pub client: reqwest::Client,
     pub client_with_middleware: ClientWithMiddleware,
     pub headers: HeaderMap,
+    pub retry_policy: RetryPolicy,
+    /// 创建客户端时使用的代理，登录等需要单独创建客户端的场景沿用此代理
+    pub proxy: Option<Proxy>,
 }
 
 impl StatelessClient {
-    pub fn new(headers: HeaderMap) -> Self {
-        let client = reqwest::Client::builder()
+    /// `proxy` 为空时使用 HTTP_PROXY 等环境变量中的代理
+    pub fn new(headers: HeaderMap, proxy: Option<Proxy>) -> Self {
+        let client = proxied(reqwest::Client::builder(), proxy.as_ref())
             .user_agent("Mozilla/5.0 (X11; Linux x86_64; rv:60.1) Gecko/20100101 Firefox/60.1")
             .default_headers(headers)
             // .timeout(Duration::new(60, 0))
             .connect_timeout(Duration::from_secs(60))
             .build()
             .unwrap();
-        let retry_policy = ExponentialBackoff::builder().build_with_max_retries(5);
-        let client_with_middleware = ClientBuilder::new(client.clone())
-            // Retry failed requests.
-            .with(RetryTransientMiddleware::new_with_policy(retry_policy))
-            .build();
         Self {
+            client_with_middleware: with_retry(client.clone(), &RetryPolicy::default()),
             client,
-            client_with_middleware,
             headers: HeaderMap::new(),
+            retry_policy: RetryPolicy::default(),
+            proxy,
         }
     }
 
+    /// 替换重试策略，`client_with_middleware` 与上传线路也会使用新的策略
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.client_with_middleware = with_retry(self.client.clone(), &retry_policy);
+        self.retry_policy = retry_policy;
+        self
+    }
+
     pub async fn retryable(&self, url: &str) -> reqwest::Result<Response> {
-        let resp = retry(|| {
-            self.client
-                .get(url)
-                .headers(self.headers.clone())
-                // .timeout(Duration::MAX)
-                // .header(ACCEPT, "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
-                // .header(ACCEPT_ENCODING, "gzip, deflate")
-                // .header(ACCEPT_LANGUAGE, "zh-CN,zh;q=0.8,en-US;q=0.5,en;q=0.3")
-                // .header(USER_AGENT, "Mozilla/5.0 (X11; Linux x86_64; rv:38.0) Gecko/20100101 Firefox/38.0 Iceweasel/38.2.1")
-                // .headers(headers.clone())
-                .send()
-        })
-        .await?;
-        resp.error_for_status_ref()?;
-        Ok(resp)
+        self.retry_policy
+            .retry(|| async {
+                self.client
+                    .get(url)
+                    .headers(self.headers.clone())
+                    // .timeout(Duration::MAX)
+                    // .header(ACCEPT, "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")
+                    // .header(ACCEPT_ENCODING, "gzip, deflate")
+                    // .header(ACCEPT_LANGUAGE, "zh-CN,zh;q=0.8,en-US;q=0.5,en;q=0.3")
+                    // .header(USER_AGENT, "Mozilla/5.0 (X11; Linux x86_64; rv:38.0) Gecko/20100101 Firefox/38.0 Iceweasel/38.2.1")
+                    // .headers(headers.clone())
+                    .send()
+                    .await?
+                    .error_for_status()
+            })
+            .await
     }
 }
 
@@ -64,12 +72,12 @@ pub struct StatefulClient {
 }
 
 impl StatefulClient {
-    pub fn new(headers: HeaderMap) -> Self {
+    pub fn new(headers: HeaderMap, proxy: Option<&Proxy>) -> Self {
         let cookie_store = reqwest_cookie_store::CookieStore::default();
         let cookie_store = CookieStoreMutex::new(cookie_store);
         let cookie_store = Arc::new(cookie_store);
         StatefulClient {
-            client: reqwest::Client::builder()
+            client: proxied(reqwest::Client::builder(), proxy)
                 .cookie_provider(std::sync::Arc::clone(&cookie_store))
                 .user_agent(
                     "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 Chrome/63.0.3239.108",
@@ -87,10 +95,31 @@ impl StatefulClient {
 
 impl Default for StatelessClient {
     fn default() -> Self {
-        Self::new(header::HeaderMap::new())
+        Self::new(header::HeaderMap::new(), None)
     }
 }
 
+/// 支持 http、https、socks5 与 socks5h 代理，如 `socks5h://127.0.0.1:1080`
+pub fn proxied(builder: HttpClientBuilder, proxy: Option<&Proxy>) -> HttpClientBuilder {
+    match proxy {
+        Some(proxy) => builder.proxy(proxy.clone()),
+        None => builder,
+    }
+}
+
+pub(crate) fn with_retry(
+    client: reqwest::Client,
+    retry_policy: &RetryPolicy,
+) -> ClientWithMiddleware {
+    ClientBuilder::new(client)
+        // Retry failed requests.
+        .with(RetryTransientMiddleware::new_with_policy_and_strategy(
+            retry_policy.backoff(),
+            retry_policy.clone(),
+        ))
+        .build()
+}
+
 // ref: https://github.com/SocialSisterYi/bilibili-API-collect
 fn generate_buvid() -> String {
     let mut rng = rand::thread_rng();
@@ -110,3 +139,32 @@ fn generate_buvid() -> String {
 
     format!("Y{hash_string}")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header as header_eq, method};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn requests_go_through_proxy() {
+        let proxy = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header_eq("host", "member.bilibili.invalid"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("proxied"))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+        let client = StatelessClient::new(HeaderMap::new(), Some(Proxy::all(proxy.uri()).unwrap()));
+        let body = client
+            .client
+            .get("http://member.bilibili.invalid/x/web/nav")
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap();
+        assert_eq!(body, "proxied");
+    }
+}
@@ -6,9 +6,11 @@ use async_trait::async_trait;
 use reqwest::header::{HeaderValue, ACCEPT_ENCODING};
 use std::any::Any;
 use std::fmt::{Display, Formatter};
+use std::path::Path;
 use tracing::info;
 
 use crate::client::StatelessClient;
+use crate::uploader::FinishSignal;
 
 mod bilibili;
 mod douyu;
@@ -52,6 +54,8 @@ pub enum Extension {
 }
 
 pub type CallbackFn = Box<dyn Fn(&str) + Send>;
+/// 收到仍在写入的 `.part` 文件及其写入结束的信号
+pub type TailCallbackFn = Box<dyn Fn(&Path, FinishSignal) + Send>;
 
 impl Site {
     pub async fn download(
@@ -59,6 +63,18 @@ impl Site {
         fmt_file_name: &str,
         segment: Segmentable,
         hook: Option<CallbackFn>,
+    ) -> downloader::error::Result<()> {
+        self.download_with_tail(fmt_file_name, segment, hook, None)
+            .await
+    }
+
+    /// 与 `download` 相同，另外在每个分段文件创建时调用 `tail_hook`
+    pub async fn download_with_tail(
+        &mut self,
+        fmt_file_name: &str,
+        segment: Segmentable,
+        hook: Option<CallbackFn>,
+        tail_hook: Option<TailCallbackFn>,
     ) -> downloader::error::Result<()> {
         let fmt_file_name = fmt_file_name.replace("{title}", &self.title);
         self.client
@@ -67,14 +83,15 @@ impl Site {
         info!("{}", self);
         match self.extension {
             Extension::Flv => {
-                let file = LifecycleFile::new(&fmt_file_name, "flv", hook);
+                let file =
+                    LifecycleFile::new(&fmt_file_name, "flv", hook).with_tail_hook(tail_hook);
                 let response = self.client.retryable(&self.direct_url).await?;
                 let mut connection = Connection::new(response);
                 connection.read_frame(9).await?;
                 httpflv::parse_flv(connection, file, segment).await?
             }
             Extension::Ts => {
-                let file = LifecycleFile::new(&fmt_file_name, "ts", hook);
+                let file = LifecycleFile::new(&fmt_file_name, "ts", hook).with_tail_hook(tail_hook);
                 hls::download(&self.direct_url, &self.client, file, segment).await?
             }
         }
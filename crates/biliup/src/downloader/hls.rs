@@ -5,7 +5,7 @@ use m3u8_rs::Playlist;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::time::Duration;
-use tracing::{debug, info, warn};
+use tracing::{debug, error, info, warn};
 use url::Url;
 
 use crate::client::StatelessClient;
@@ -53,8 +53,7 @@ pub async fn download(
             info!("Segments array is empty - stream finished");
             break;
         }
-        let mut seq = pl.media_sequence;
-        for segment in &pl.segments {
+        for (seq, segment) in (pl.media_sequence..).zip(pl.segments.iter()) {
             if seq > previous_last_segment {
                 if (previous_last_segment > 0) && (seq > (previous_last_segment + 1)) {
                     warn!("SEGMENT INFO SKIPPED");
@@ -80,7 +79,6 @@ pub async fn download(
                 }
                 previous_last_segment = seq;
             }
-            seq += 1;
         }
         let resp = client.retryable(media_url.as_str()).await?;
         let bs = resp.bytes().await?;
@@ -92,14 +90,25 @@ pub async fn download(
     Ok(())
 }
 
+/// 分片较小，完整读取后再写入文件，读取中途出错时整个分片按重试策略重新下载
 async fn download_to_file(url: Url, client: &StatelessClient, out: &mut impl Write) -> Result<u64> {
     debug!("url: {url}");
-    let mut response = client.retryable(url.as_str()).await?;
-    let mut length: u64 = 0;
-    while let Some(chunk) = response.chunk().await? {
-        length += chunk.len() as u64;
-        out.write_all(&chunk)?;
-    }
+    let segment = client
+        .retry_policy
+        .retry(|| async {
+            client
+                .client
+                .get(url.clone())
+                .headers(client.headers.clone())
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await
+        })
+        .await?;
+    out.write_all(&segment)?;
+    let length = segment.len() as u64;
     // let mut out = File::options()
     //     .append(true)
     //     .open(format!("{file_name}.ts"))?;
@@ -122,6 +131,8 @@ impl TsFile {
     }
 
     pub fn create_new(&mut self) -> std::io::Result<()> {
+        // 写完缓冲区再发出写入结束的信号，边录边传才能读到完整的文件
+        self.buf_writer.flush()?;
         self.file.rename();
         let path = self.file.create()?;
         self.buf_writer = Self::create(path)?;
@@ -146,6 +157,9 @@ impl TsFile {
 
 impl Drop for TsFile {
     fn drop(&mut self) {
+        if let Err(e) = self.buf_writer.flush() {
+            error!("flush {}: {e}", self.file.path.display());
+        }
         self.file.rename()
     }
 }
@@ -5,7 +5,8 @@ use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tracing::{error, info};
 
-use super::extractor::CallbackFn;
+use super::extractor::{CallbackFn, TailCallbackFn};
+use crate::uploader::FinishSignal;
 
 #[derive(Debug)]
 pub enum Segment {
@@ -103,6 +104,9 @@ pub struct LifecycleFile {
     pub path: PathBuf,
     pub hook: CallbackFn,
     pub extension: &'static str,
+    /// 文件创建后立即调用，用于边录边传
+    pub tail_hook: Option<TailCallbackFn>,
+    finished: FinishSignal,
 }
 
 impl LifecycleFile {
@@ -118,9 +122,16 @@ impl LifecycleFile {
             path: Default::default(),
             hook,
             extension,
+            tail_hook: None,
+            finished: FinishSignal::default(),
         }
     }
 
+    pub fn with_tail_hook(mut self, tail_hook: Option<TailCallbackFn>) -> Self {
+        self.tail_hook = tail_hook;
+        self
+    }
+
     pub fn create(&mut self) -> Result<&Path, std::io::Error> {
         self.file_name = format!(
             "{}.{}",
@@ -134,10 +145,17 @@ impl LifecycleFile {
         // path.set_extension(&self.extension);
         self.path.set_extension(format!("{}.part", self.extension));
         info!("Save to {}", self.path.display());
+        if let Some(tail_hook) = &self.tail_hook {
+            // 先创建文件，上传端才能在写入开始前打开它
+            fs::File::create(&self.path)?;
+            std::mem::take(&mut self.finished).finish();
+            tail_hook(&self.path, self.finished.clone());
+        }
         Ok(self.path.as_path())
     }
 
     pub fn rename(&self) {
+        self.finished.finish();
         match fs::rename(&self.path, &self.file_name) {
             Ok(_) => (self.hook)(&self.file_name),
             Err(e) => {
@@ -147,6 +165,13 @@ impl LifecycleFile {
     }
 }
 
+/// 下载出错或中断时也要结束信号，否则边录边传的上传会一直等待新数据
+impl Drop for LifecycleFile {
+    fn drop(&mut self) {
+        self.finished.finish();
+    }
+}
+
 pub fn format_filename(file_name: &str) -> String {
     let local: DateTime<Local> = Local::now();
     // let time_str = local.format("%Y-%m-%dT%H_%M_%S");
@@ -157,8 +182,10 @@ pub fn format_filename(file_name: &str) -> String {
 
 #[cfg(test)]
 mod tests {
+    use super::LifecycleFile;
     use anyhow::Result;
     use std::path::{Path, PathBuf};
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn it_works() -> Result<()> {
@@ -172,4 +199,36 @@ mod tests {
 
         Ok(())
     }
+    #[test]
+    fn tail_hook_receives_part_file() -> Result<()> {
+        let received = Arc::new(Mutex::new(None));
+        let fmt_file_name = std::env::temp_dir().join("biliup-tail-hook-test");
+        let mut file = LifecycleFile::new(fmt_file_name.to_str().unwrap(), "flv", None)
+            .with_tail_hook(Some({
+                let received = received.clone();
+                Box::new(move |path: &Path, finished| {
+                    *received.lock().unwrap() = Some((path.to_path_buf(), finished));
+                })
+            }));
+        let part = file.create()?.to_path_buf();
+        let (path, finished) = received
+            .lock()
+            .unwrap()
+            .take()
+            .expect("tail hook not called");
+        assert_eq!(path, part);
+        assert!(path.exists());
+        assert!(!finished.is_finished());
+        file.rename();
+        assert!(finished.is_finished());
+        std::fs::remove_file(&file.file_name)?;
+
+        // 未能重命名就被丢弃时同样结束
+        let part = file.create()?.to_path_buf();
+        let (_, finished) = received.lock().unwrap().take().unwrap();
+        drop(file);
+        assert!(finished.is_finished());
+        std::fs::remove_file(part)?;
+        Ok(())
+    }
 }
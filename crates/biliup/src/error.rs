@@ -1,5 +1,6 @@
 use reqwest::header::{InvalidHeaderName, InvalidHeaderValue};
 
+use std::time::Duration;
 use thiserror::Error;
 
 pub type Result<T> = core::result::Result<T, Kind>;
@@ -41,6 +42,142 @@ pub enum Kind {
 
     #[error("need recaptcha")]
     NeedRecaptcha(String),
+
+    #[error(transparent)]
+    BiliApi(#[from] BiliApiError),
+
+    #[error(transparent)]
+    Invalid(#[from] crate::uploader::validate::ValidationError),
+}
+
+impl Kind {
+    /// 错误来自B站接口的返回码时返回该错误
+    pub fn api_error(&self) -> Option<&BiliApiError> {
+        match self {
+            Kind::BiliApi(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// B站接口返回码不为 0 时的错误
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("{message} ({code})")]
+pub struct BiliApiError {
+    pub code: i32,
+    pub message: String,
+}
+
+/// 常见的接口返回码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiErrorCode {
+    /// -101 账号未登录
+    NotLoggedIn,
+    /// -2 access_key 错误，61000 登录状态失效
+    TokenExpired,
+    /// -111 csrf 校验失败
+    CsrfFailed,
+    /// -400 请求错误
+    BadRequest,
+    /// -403 权限不足
+    Forbidden,
+    /// -404 稿件或资源不存在
+    NotFound,
+    /// -412 请求被拦截
+    Blocked,
+    /// -509 请求过于频繁
+    TooManyRequests,
+    /// 21540 投稿过于频繁
+    SubmitTooFrequent,
+    /// 21012 标题过长
+    TitleTooLong,
+    /// 21020 短时间内重复提交相同的稿件
+    DuplicateSubmission,
+    /// -500 服务器错误，-503 服务繁忙，-504 服务调用超时
+    ServerError,
+    Other(i32),
+}
+
+impl From<i32> for ApiErrorCode {
+    fn from(code: i32) -> Self {
+        match code {
+            -101 => ApiErrorCode::NotLoggedIn,
+            -2 | 61000 => ApiErrorCode::TokenExpired,
+            -111 => ApiErrorCode::CsrfFailed,
+            -400 => ApiErrorCode::BadRequest,
+            -403 => ApiErrorCode::Forbidden,
+            -404 => ApiErrorCode::NotFound,
+            -412 => ApiErrorCode::Blocked,
+            -509 => ApiErrorCode::TooManyRequests,
+            21540 => ApiErrorCode::SubmitTooFrequent,
+            21012 => ApiErrorCode::TitleTooLong,
+            21020 => ApiErrorCode::DuplicateSubmission,
+            -500 | -503 | -504 => ApiErrorCode::ServerError,
+            code => ApiErrorCode::Other(code),
+        }
+    }
+}
+
+impl BiliApiError {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    /// 从接口返回的 json 中读取 `code` 与 `message`
+    pub fn from_value(value: &serde_json::Value) -> Self {
+        Self::new(
+            value["code"].as_i64().unwrap_or_default() as i32,
+            value["message"].as_str().unwrap_or_default(),
+        )
+    }
+
+    pub fn kind(&self) -> ApiErrorCode {
+        self.code.into()
+    }
+
+    /// 稍后重试可能成功的错误
+    pub fn is_retryable(&self) -> bool {
+        self.retry_delay().is_some()
+    }
+
+    /// 限流导致的失败，服务器未处理该请求，投稿等非幂等的请求也可以重试
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(
+            self.kind(),
+            ApiErrorCode::SubmitTooFrequent | ApiErrorCode::TooManyRequests | ApiErrorCode::Blocked
+        )
+    }
+
+    /// 重试前建议等待的时间，不可重试时为空
+    pub fn retry_delay(&self) -> Option<Duration> {
+        match self.kind() {
+            ApiErrorCode::SubmitTooFrequent => Some(Duration::from_secs(5 * 60)),
+            ApiErrorCode::TooManyRequests | ApiErrorCode::Blocked => Some(Duration::from_secs(60)),
+            ApiErrorCode::ServerError => Some(Duration::from_secs(10)),
+            _ => None,
+        }
+    }
+
+    /// 稿件标题超过长度限制，需要修改后重新提交
+    pub fn is_title_too_long(&self) -> bool {
+        self.kind() == ApiErrorCode::TitleTooLong
+    }
+
+    /// 相同的稿件刚刚提交过，不应再次提交
+    pub fn is_duplicate(&self) -> bool {
+        self.kind() == ApiErrorCode::DuplicateSubmission
+    }
+
+    /// 登录信息失效，需要重新登录或刷新登录信息
+    pub fn needs_relogin(&self) -> bool {
+        matches!(
+            self.kind(),
+            ApiErrorCode::NotLoggedIn | ApiErrorCode::TokenExpired | ApiErrorCode::CsrfFailed
+        )
+    }
 }
 
 impl From<&str> for Kind {
@@ -48,3 +185,37 @@ impl From<&str> for Kind {
         Self::Custom(s.into())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn classifies_api_codes() {
+        let e = BiliApiError::from_value(&json!({"code": 21540, "message": "投稿过于频繁"}));
+        assert_eq!(e.kind(), ApiErrorCode::SubmitTooFrequent);
+        assert!(e.is_retryable() && e.is_rate_limited());
+        assert!(!e.needs_relogin());
+        assert_eq!(e.to_string(), "投稿过于频繁 (21540)");
+
+        let e = BiliApiError::new(-101, "账号未登录");
+        assert!(e.needs_relogin());
+        assert!(!e.is_retryable());
+
+        let e = BiliApiError::new(21012, "标题过长");
+        assert_eq!(e.kind(), ApiErrorCode::TitleTooLong);
+        assert!(e.is_title_too_long() && !e.is_duplicate() && !e.is_retryable());
+
+        let e = BiliApiError::new(21020, "请勿重复提交");
+        assert_eq!(e.kind(), ApiErrorCode::DuplicateSubmission);
+        assert!(e.is_duplicate() && !e.is_title_too_long() && !e.is_retryable());
+
+        let e = BiliApiError::new(-503, "服务器错误");
+        assert!(e.is_retryable() && !e.is_rate_limited());
+        assert_eq!(
+            BiliApiError::new(21001, "").kind(),
+            ApiErrorCode::Other(21001)
+        );
+    }
+}
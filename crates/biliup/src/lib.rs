@@ -1,12 +1,10 @@
+use crate::retry_policy::RetryPolicy;
 use futures::Stream;
-use rand::distributions::uniform::{UniformFloat, UniformSampler};
 use std::future::Future;
-use std::time::Duration;
-use tokio::time::sleep;
-use tracing::info;
 pub mod client;
 pub mod downloader;
 pub mod error;
+pub mod retry_policy;
 #[cfg(feature = "server")]
 pub mod server;
 pub mod uploader;
@@ -14,33 +12,25 @@ pub mod uploader;
 pub use uploader::bilibili;
 pub use uploader::credential;
 
-pub async fn retry<F, Fut, O, E: std::fmt::Display>(mut f: F) -> Result<O, E>
+/// 使用默认的 [`RetryPolicy`] 重试所有错误
+pub async fn retry<F, Fut, O, E: std::fmt::Display>(f: F) -> Result<O, E>
 where
     F: FnMut() -> Fut,
     Fut: Future<Output = Result<O, E>>,
 {
-    let mut retries = 3;
-    let mut wait = 1;
-    let mut jittered_wait_for;
-    loop {
-        match f().await {
-            Err(e) if retries > 0 => {
-                retries -= 1;
-                let jitter_factor =
-                    UniformFloat::<f64>::sample_single(0., 1., &mut rand::thread_rng());
-                wait *= 2;
+    retry_notify(f, |_, _| {}).await
+}
 
-                jittered_wait_for = f64::min(jitter_factor + (wait as f64), 64.);
-                info!(
-                    "Retry attempt #{}. Sleeping {:?} before the next attempt. {e}",
-                    3 - retries,
-                    jittered_wait_for
-                );
-                sleep(Duration::from_secs_f64(jittered_wait_for)).await;
-            }
-            res => break res,
-        }
-    }
+/// 与 [`retry`] 相同，每次重试前以重试次数和上次的错误调用 `notify`
+pub async fn retry_notify<F, Fut, O, E: std::fmt::Display>(
+    f: F,
+    notify: impl FnMut(usize, &E),
+) -> Result<O, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<O, E>>,
+{
+    RetryPolicy::default().retry_if(f, |_| true, notify).await
 }
 
 #[cfg(test)]
@@ -0,0 +1,325 @@
+use crate::error::Kind;
+use rand::Rng;
+use reqwest::StatusCode;
+use reqwest_retry::policies::ExponentialBackoff;
+use reqwest_retry::{Retryable, RetryableStrategy};
+use retry_policies::Jitter;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::future::Future;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::info;
+
+/// 重试策略，上传分块、合并分块与下载请求共用，通过 [`StatelessClient`](crate::client::StatelessClient) 传递。
+/// 配置文件中的时间使用 `2s`、`1m` 这样的格式
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 最多重试的次数，不含第一次请求
+    pub max_retries: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍
+    #[serde(with = "humantime_serde")]
+    pub base_delay: Duration,
+    /// 等待时间的上限
+    #[serde(with = "humantime_serde")]
+    pub max_delay: Duration,
+    /// 在等待时间上随机增加 0 到 `jitter`，避免多个分块同时重试
+    #[serde(with = "humantime_serde")]
+    pub jitter: Duration,
+    /// 是否重试 4xx 错误，408 与 429 总是会重试
+    pub client_errors: bool,
+    /// 是否重试 5xx 错误
+    pub server_errors: bool,
+    /// 是否重试超时
+    pub timeouts: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(64),
+            jitter: Duration::from_secs(1),
+            client_errors: false,
+            server_errors: true,
+            timeouts: true,
+        }
+    }
+}
+
+/// 错误的分类，决定是否重试
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// 4xx，不含 408 与 429
+    ClientError,
+    /// 5xx
+    ServerError,
+    Timeout,
+    /// 构造请求或解码失败等重试也无法恢复的错误
+    Fatal,
+    /// 连接中断、校验失败等其他错误，总是重试
+    Other,
+}
+
+pub trait RetryableError: Display {
+    fn class(&self) -> ErrorClass;
+}
+
+impl ErrorClass {
+    fn from_status(status: StatusCode) -> Option<Self> {
+        match status {
+            StatusCode::REQUEST_TIMEOUT | StatusCode::TOO_MANY_REQUESTS => Some(ErrorClass::Other),
+            status if status.is_client_error() => Some(ErrorClass::ClientError),
+            status if status.is_server_error() => Some(ErrorClass::ServerError),
+            _ => None,
+        }
+    }
+}
+
+impl RetryableError for reqwest::Error {
+    fn class(&self) -> ErrorClass {
+        if self.is_timeout() {
+            ErrorClass::Timeout
+        } else if let Some(class) = self.status().and_then(ErrorClass::from_status) {
+            class
+        } else if self.is_builder() || self.is_redirect() || self.is_decode() {
+            ErrorClass::Fatal
+        } else {
+            ErrorClass::Other
+        }
+    }
+}
+
+impl RetryableError for reqwest_middleware::Error {
+    fn class(&self) -> ErrorClass {
+        match self {
+            reqwest_middleware::Error::Reqwest(e) => e.class(),
+            reqwest_middleware::Error::Middleware(_) => ErrorClass::Other,
+        }
+    }
+}
+
+impl RetryableError for Kind {
+    fn class(&self) -> ErrorClass {
+        match self {
+            Kind::Reqwest(e) => e.class(),
+            Kind::ReqwestMiddleware(e) => e.class(),
+            Kind::InvalidHeaderValue(_) | Kind::InvalidHeaderName(_) => ErrorClass::Fatal,
+            _ => ErrorClass::Other,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn should_retry(&self, error: &impl RetryableError) -> bool {
+        match error.class() {
+            ErrorClass::ClientError => self.client_errors,
+            ErrorClass::ServerError => self.server_errors,
+            ErrorClass::Timeout => self.timeouts,
+            ErrorClass::Fatal => false,
+            ErrorClass::Other => true,
+        }
+    }
+
+    /// 第 `attempt` 次重试前的等待时间，`attempt` 从 1 开始
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        let jitter = if self.jitter.is_zero() {
+            Duration::ZERO
+        } else {
+            rand::thread_rng().gen_range(Duration::ZERO..self.jitter)
+        };
+        (exponential + jitter).min(self.max_delay)
+    }
+
+    pub async fn retry<F, Fut, O, E>(&self, f: F) -> Result<O, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<O, E>>,
+        E: RetryableError,
+    {
+        self.retry_notify(f, |_, _| {}).await
+    }
+
+    /// 与 [`retry`](Self::retry) 相同，每次重试前以重试次数和上次的错误调用 `notify`
+    pub async fn retry_notify<F, Fut, O, E>(
+        &self,
+        f: F,
+        notify: impl FnMut(usize, &E),
+    ) -> Result<O, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<O, E>>,
+        E: RetryableError,
+    {
+        self.retry_if(f, |e| self.should_retry(e), notify).await
+    }
+
+    /// 由 `should_retry` 决定哪些错误需要重试
+    pub async fn retry_if<F, Fut, O, E: Display>(
+        &self,
+        mut f: F,
+        should_retry: impl Fn(&E) -> bool,
+        mut notify: impl FnMut(usize, &E),
+    ) -> Result<O, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<O, E>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match f().await {
+                Err(e) if attempt < self.max_retries && should_retry(&e) => {
+                    attempt += 1;
+                    let wait = self.delay(attempt);
+                    info!(
+                        "Retry attempt #{attempt}. Sleeping {wait:?} before the next attempt. {e}"
+                    );
+                    notify(attempt as usize, &e);
+                    sleep(wait).await;
+                }
+                res => break res,
+            }
+        }
+    }
+
+    /// 供 `RetryTransientMiddleware` 使用的退避策略
+    pub fn backoff(&self) -> ExponentialBackoff {
+        ExponentialBackoff::builder()
+            .retry_bounds(self.base_delay.min(self.max_delay), self.max_delay)
+            .jitter(if self.jitter.is_zero() {
+                Jitter::None
+            } else {
+                Jitter::Bounded
+            })
+            .build_with_max_retries(self.max_retries)
+    }
+}
+
+impl RetryableStrategy for RetryPolicy {
+    fn handle(
+        &self,
+        res: &Result<reqwest::Response, reqwest_middleware::Error>,
+    ) -> Option<Retryable> {
+        let retryable = |retry| {
+            if retry {
+                Retryable::Transient
+            } else {
+                Retryable::Fatal
+            }
+        };
+        match res {
+            Ok(response) => ErrorClass::from_status(response.status()).map(|class| {
+                retryable(match class {
+                    ErrorClass::ClientError => self.client_errors,
+                    ErrorClass::ServerError => self.server_errors,
+                    _ => true,
+                })
+            }),
+            Err(e) => Some(retryable(self.should_retry(e))),
+        }
+    }
+}
+
+pub(crate) mod humantime_serde {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&humantime::format_duration(*duration))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        humantime::parse_duration(&s).map_err(serde::de::Error::custom)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::time::Duration;
+
+        pub fn serialize<S: Serializer>(
+            duration: &Option<Duration>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match duration {
+                Some(duration) => super::serialize(duration, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Duration>, D::Error> {
+            Option::<String>::deserialize(deserializer)?
+                .map(|s| humantime::parse_duration(&s).map_err(serde::de::Error::custom))
+                .transpose()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn delay_doubles_up_to_cap() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_secs(2),
+            max_delay: Duration::from_secs(5),
+            jitter: Duration::ZERO,
+            ..Default::default()
+        };
+        assert_eq!(policy.delay(1), Duration::from_secs(2));
+        assert_eq!(policy.delay(2), Duration::from_secs(4));
+        assert_eq!(policy.delay(3), Duration::from_secs(5));
+        assert_eq!(policy.delay(40), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn policy_from_yaml() {
+        let policy: RetryPolicy =
+            serde_yaml::from_str("max_retries: 5\nbase_delay: 500ms\nclient_errors: true").unwrap();
+        assert_eq!(policy.max_retries, 5);
+        assert_eq!(policy.base_delay, Duration::from_millis(500));
+        assert_eq!(policy.max_delay, Duration::from_secs(64));
+        assert!(policy.client_errors);
+    }
+
+    #[tokio::test]
+    async fn retries_only_retryable_errors() {
+        let policy = RetryPolicy {
+            base_delay: Duration::from_millis(1),
+            jitter: Duration::ZERO,
+            ..Default::default()
+        };
+        let calls = AtomicUsize::new(0);
+        let result: Result<(), Kind> = policy
+            .retry(|| async {
+                calls.fetch_add(1, Ordering::Relaxed);
+                Err(Kind::Custom("md5 mismatch".into()))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 4);
+
+        let server = wiremock::MockServer::start().await;
+        wiremock::Mock::given(wiremock::matchers::any())
+            .respond_with(wiremock::ResponseTemplate::new(403))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let result = policy
+            .retry(|| async {
+                reqwest::get(server.uri()).await?.error_for_status()?;
+                Ok::<_, Kind>(())
+            })
+            .await;
+        assert!(result.is_err());
+    }
+}
@@ -3,12 +3,17 @@ use crate::server::core::live_streamers::{
     AddLiveStreamerDto, DynLiveStreamersRepository, DynLiveStreamersService, LiveStreamerDto,
     LiveStreamerEntity,
 };
+use crate::server::core::upload_progress::{ProgressSnapshot, UploadProgress};
 use crate::server::core::upload_streamers::{DynUploadStreamersRepository, StudioEntity};
 use crate::server::core::users::{DynUsersRepository, User};
 use crate::server::errors::AppResult;
 
 use axum::extract::{Path, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
 use axum::{Extension, Json};
+use futures::Stream;
+use std::convert::Infallible;
+use tokio::sync::broadcast::error::RecvError;
 
 pub async fn get_streamers_endpoint(
     Extension(streamers_service): Extension<DynLiveStreamersService>,
@@ -110,3 +115,28 @@ pub async fn delete_user_endpoint(
 ) -> AppResult<Json<()>> {
     Ok(Json(state.delete_user(id).await?))
 }
+
+pub async fn upload_progress_endpoint(
+    Extension(progress): Extension<UploadProgress>,
+) -> Json<ProgressSnapshot> {
+    Json(progress.snapshot())
+}
+
+/// 以 Server-Sent Events 推送上传事件，落后太多的订阅者会丢失部分事件
+pub async fn upload_events_endpoint(
+    Extension(progress): Extension<UploadProgress>,
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = futures::stream::unfold(progress.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let event = Event::default().json_data(event).unwrap_or_default();
+                    return Some((Ok(event), receiver));
+                }
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
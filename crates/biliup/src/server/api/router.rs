@@ -5,7 +5,7 @@ use crate::server::api::endpoints::{
     delete_streamer_endpoint, delete_template_endpoint, delete_user_endpoint,
     get_streamer_endpoint, get_streamers_endpoint, get_upload_streamer_endpoint,
     get_upload_streamers_endpoint, get_users_endpoint, update_streamer_endpoint,
-    update_template_endpoint,
+    update_template_endpoint, upload_events_endpoint, upload_progress_endpoint,
 };
 use crate::server::core::download_actor::DownloadActorHandle;
 
@@ -19,6 +19,7 @@ use crate::server::api::bilibili_endpoints::{
     archive_pre_endpoint, get_myinfo_endpoint, get_proxy_endpoint,
 };
 use crate::server::core::main_loop::spawn_main_loop;
+use crate::server::core::upload_actor::UploadSettings;
 use axum::http::HeaderValue;
 use std::net::SocketAddr;
 
@@ -28,14 +29,21 @@ use tracing::info;
 pub struct ApplicationController;
 
 impl ApplicationController {
-    pub async fn serve(addr: &SocketAddr, service_register: ServiceRegister) -> anyhow::Result<()> {
-        let client = StatelessClient::default();
+    pub async fn serve(
+        addr: &SocketAddr,
+        service_register: ServiceRegister,
+        settings: UploadSettings,
+        download_client: StatelessClient,
+    ) -> anyhow::Result<()> {
+        let client = settings.client.clone();
         let vec = service_register.streamers_service.get_streamers().await?;
         let (main_loop, _) = spawn_main_loop();
+        let progress = settings.progress.clone();
         let actor_handle = DownloadActorHandle::new(
             vec,
-            client.clone(),
+            download_client,
             service_register.streamers_service.clone(),
+            settings,
         );
         // build our application with a route
         let app = Router::new()
@@ -58,6 +66,8 @@ impl ApplicationController {
             )
             // .route("/v1/upload/streamers/:id", )
             .route("/v1/upload/streamers", post(add_upload_streamer_endpoint))
+            .route("/v1/upload/progress", get(upload_progress_endpoint))
+            .route("/v1/upload/events", get(upload_events_endpoint))
             .route("/v1/users", get(get_users_endpoint).post(add_user_endpoint))
             .route("/v1/users/:id", delete(delete_user_endpoint))
             .route("/bili/archive/pre", get(archive_pre_endpoint))
@@ -78,6 +88,7 @@ impl ApplicationController {
             )
             .layer(Extension(service_register.streamers_service.clone()))
             .layer(Extension(actor_handle))
+            .layer(Extension(progress))
             // .layer(Extension(client.clone()))
             .layer(Extension(
                 service_register.upload_streamers_repository.clone(),
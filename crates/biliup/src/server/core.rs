@@ -4,6 +4,7 @@ pub mod download_actor;
 pub mod live_streamers;
 pub mod main_loop;
 pub mod upload_actor;
+pub mod upload_progress;
 pub mod upload_streamers;
 pub mod users;
 pub mod util;
@@ -1,9 +1,9 @@
 use crate::client::StatelessClient;
 
-use crate::downloader::extractor::{find_extractor, SiteDefinition};
+use crate::downloader::extractor::{find_extractor, CallbackFn, SiteDefinition, TailCallbackFn};
 use crate::downloader::util::Segmentable;
 use crate::server::core::live_streamers::{DynLiveStreamersService, LiveStreamerDto};
-use crate::server::core::upload_actor::UploadActorHandle;
+use crate::server::core::upload_actor::{UploadActorHandle, UploadSettings, MIN_FILE_SIZE};
 use crate::server::core::util::{logging_spawn, AnyMap, Cycle};
 use crate::server::core::StreamStatus;
 
@@ -23,6 +23,7 @@ async fn start_monitor(
     extractor: &(dyn SiteDefinition + Send + Sync),
     client: StatelessClient,
     live_streamers_service: DynLiveStreamersService,
+    settings: UploadSettings,
 ) {
     let n = &mut 0;
     loop {
@@ -47,34 +48,48 @@ async fn start_monitor(
                 };
                 let live_streamers_service = live_streamers_service.clone();
                 {
-                    let client = client.clone();
+                    let settings = settings.clone();
                     let url = url.clone();
                     let task = task.clone();
                     logging_spawn(async move {
-                        let hook = live_streamers_service
+                        let tail = settings.tail;
+                        let handle = live_streamers_service
                             .get_studio_by_url(&url)
                             .await
                             .unwrap_or_default()
-                            .map(|studio| -> Box<dyn Fn(&str) + Send> {
-                                let handle = UploadActorHandle::new(client, studio);
-                                Box::new(move |file_name| {
+                            .map(|studio| UploadActorHandle::new(studio, settings));
+                        let (hook, tail_hook) = match handle {
+                            // 边录边传时文件大小未知，写入结束后再过滤过小的分段
+                            Some(handle) if tail => {
+                                let tail_hook: TailCallbackFn = Box::new(move |path, finished| {
+                                    info!("开始边录边传: {}", path.display());
+                                    handle.send_growing_file(path, finished);
+                                });
+                                (None, Some(tail_hook))
+                            }
+                            Some(handle) => {
+                                let hook: CallbackFn = Box::new(move |file_name| {
                                     if let Ok(metadata) = std::fs::metadata(file_name)
                                         .map_err(|err| error!("{}", err))
                                     {
-                                        if metadata.len() > 10 * 1024 * 1024 {
+                                        if metadata.len() > MIN_FILE_SIZE {
                                             info!("开始上传: {}", file_name);
                                             handle.send_file_path(file_name);
                                         }
                                     }
-                                })
-                            });
-                        if hook.is_none() {
-                            debug!(url = %url, "upload template not set.");
-                        }
+                                });
+                                (Some(hook), None)
+                            }
+                            None => {
+                                debug!(url = %url, "upload template not set.");
+                                (None, None)
+                            }
+                        };
 
                         let segmentable = Segmentable::new(split_time, split_size);
                         // let segmentable = Segmentable::new( None, Some(16*1024*1024));
-                        site.download(&filename, segmentable, hook).await?;
+                        site.download_with_tail(&filename, segmentable, hook, tail_hook)
+                            .await?;
                         task.change(&url, StreamStatus::Idle);
                         Ok::<_, Box<dyn Error + Send + Sync>>(())
                     });
@@ -100,13 +115,19 @@ async fn start_monitor(
 struct DownloadActor {
     live_streamers_service: DynLiveStreamersService,
     client: StatelessClient,
+    settings: UploadSettings,
 }
 
 impl DownloadActor {
-    fn new(live_streamers_service: DynLiveStreamersService, client: StatelessClient) -> Self {
+    fn new(
+        live_streamers_service: DynLiveStreamersService,
+        client: StatelessClient,
+        settings: UploadSettings,
+    ) -> Self {
         Self {
             live_streamers_service,
             client,
+            settings,
         }
     }
 
@@ -141,8 +162,9 @@ impl DownloadActor {
                 let task = cycle.clone();
                 let client = self.client.clone();
                 let live_streamers_service = self.live_streamers_service.clone();
+                let settings = self.settings.clone();
                 let handle = tokio::spawn(async move {
-                    start_monitor(task, extractor, client, live_streamers_service).await
+                    start_monitor(task, extractor, client, live_streamers_service, settings).await
                 });
                 (cycle, handle)
             });
@@ -163,8 +185,9 @@ impl DownloadActorHandle {
         list: Vec<LiveStreamerDto>,
         client: StatelessClient,
         live_streamers_service: DynLiveStreamersService,
+        settings: UploadSettings,
     ) -> Self {
-        let mut actor = DownloadActor::new(live_streamers_service, client);
+        let mut actor = DownloadActor::new(live_streamers_service, client, settings);
         let platform_map = Arc::new(RwLock::new(HashMap::default()));
         let platform = Arc::clone(&platform_map);
         // let client_c = client.clone();
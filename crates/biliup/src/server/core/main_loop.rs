@@ -1,8 +1,7 @@
-use crate::client::StatelessClient;
 use crate::downloader::extractor::Site;
 use crate::downloader::util::Segmentable;
 use crate::server::core::live_streamers::{DynLiveStreamersService, LiveStreamerDto, Videos};
-use crate::server::core::upload_actor::UploadActorHandle;
+use crate::server::core::upload_actor::{UploadActorHandle, UploadSettings, MIN_FILE_SIZE};
 use crate::server::core::util::{logging_spawn, Cycle};
 use crate::server::core::StreamStatus;
 use anyhow::Result;
@@ -67,8 +66,8 @@ async fn recording(
     url: &str,
     mut site: Site,
     task: Cycle<StreamStatus>,
-    client: StatelessClient,
     live_streamers_service: DynLiveStreamersService,
+    settings: UploadSettings,
 ) {
     println!("Idle\n {url} \n{site}");
     let (filename, split_size, split_time) = if let Ok(LiveStreamerDto {
@@ -95,12 +94,12 @@ async fn recording(
                 .await
                 .unwrap_or_default()
                 .map(|studio| -> Box<dyn Fn(&str) + Send> {
-                    let handle = UploadActorHandle::new(client, studio);
+                    let handle = UploadActorHandle::new(studio, settings);
                     Box::new(move |file_name| {
                         if let Ok(metadata) =
                             std::fs::metadata(file_name).map_err(|err| error!("{}", err))
                         {
-                            if metadata.len() > 10 * 1024 * 1024 {
+                            if metadata.len() > MIN_FILE_SIZE {
                                 info!("开始上传: {}", file_name);
                                 handle.send_file_path(file_name);
                             }
@@ -1,99 +1,271 @@
 use crate::client::StatelessClient;
 use crate::error::Kind;
-use crate::uploader::bilibili::{BiliBili, Studio, Vid, Video};
-use crate::uploader::credential::login_by_cookies;
-use crate::uploader::line::Line;
-use crate::uploader::VideoFile;
-use futures::StreamExt;
+use crate::server::core::upload_progress::UploadProgress;
+use crate::uploader::archive::WatchOptions;
+use crate::uploader::bilibili::{BiliBili, Studio, SubmitOption, Vid, Video};
+use crate::uploader::chunk_limiter::ChunkLimiter;
+use crate::uploader::credential::login_by_cookies_with;
+use crate::uploader::line::{Failover, Line, Probe, PROBE_CACHE_TTL};
+use crate::uploader::observer::{UploadEvent, UploadObserver};
+use crate::uploader::rate_limiter::RateLimiter;
+use crate::uploader::submit_scheduler::{save_unsubmitted, SubmitScheduler};
+use crate::uploader::{FinishSignal, VideoFile};
+use futures::{FutureExt, StreamExt, TryStreamExt};
 use std::path::{Path, PathBuf};
 
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
-use tracing::{error, info};
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// 小于该大小的分段不会加入稿件
+pub const MIN_FILE_SIZE: u64 = 10 * 1024 * 1024;
+
+/// 服务端上传设置，所有上传任务共享
+#[derive(Clone, Debug)]
+pub struct UploadSettings {
+    /// 上传限速，为空时不限速
+    pub rate_limiter: Option<RateLimiter>,
+    /// 同一稿件同时上传的文件数
+    pub files: usize,
+    /// 所有稿件同时上传的分块总数上限
+    pub chunk_limiter: Option<ChunkLimiter>,
+    /// 边录边传，分段文件创建后立即开始上传
+    pub tail: bool,
+    /// 上传线路，为空时按测速结果依次尝试所有线路
+    pub line: Option<Line>,
+    /// 上传进度，所有上传任务共享
+    pub progress: UploadProgress,
+    /// 上传与调用B站接口使用的客户端，代理与直播源下载分开设置
+    pub client: StatelessClient,
+    /// 投稿与追加分P使用的接口
+    pub submit: SubmitOption,
+    /// 所有上传任务共享，同一账号的投稿依次提交
+    pub scheduler: SubmitScheduler,
+}
+
+impl Default for UploadSettings {
+    fn default() -> Self {
+        Self {
+            rate_limiter: None,
+            files: 1,
+            chunk_limiter: None,
+            tail: false,
+            line: None,
+            progress: UploadProgress::new(),
+            client: StatelessClient::default(),
+            submit: SubmitOption::default(),
+            scheduler: SubmitScheduler::default(),
+        }
+    }
+}
 
 struct UploadActor {
     receiver: mpsc::UnboundedReceiver<ActorMessage>,
-    client: StatelessClient,
     studio: Studio,
+    settings: UploadSettings,
     vid: Option<Vid>,
+    /// 跟踪稿件审核进度的任务，追加分P后重新开始
+    review: Option<JoinHandle<()>>,
+    /// 投稿失败时保存的投稿信息，投稿成功后删除
+    unsubmitted: Option<PathBuf>,
 }
 enum ActorMessage {
-    Upload { path: PathBuf },
+    /// 文件在发送前就已打开，排队期间被重命名也不影响上传
+    Upload { files: Vec<VideoFile> },
 }
 
 impl UploadActor {
     fn new(
         studio: Studio,
-        client: StatelessClient,
+        settings: UploadSettings,
         receiver: mpsc::UnboundedReceiver<ActorMessage>,
     ) -> Self {
         UploadActor {
             receiver,
-            client,
             studio,
+            settings,
             vid: None,
+            review: None,
+            unsubmitted: None,
+        }
+    }
+
+    /// 投稿失败时保存已上传的分P，服务重启或没有后续分段时也不会丢失
+    fn save_unsubmitted(&mut self) {
+        self.remove_unsubmitted();
+        self.unsubmitted = save_studio(&self.studio);
+    }
+
+    fn remove_unsubmitted(&mut self) {
+        if let Some(path) = self.unsubmitted.take() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!("failed to remove {}: {e}", path.display());
+            }
         }
     }
 
+    /// 投稿或编辑成功后在后台轮询审核状态，结果作为上传事件发出
+    fn watch_review(&mut self, bili: BiliBili, vid: Vid, title: String) {
+        if let Some(review) = self.review.take() {
+            review.abort();
+        }
+        let progress = self.settings.progress.clone();
+        self.review = Some(tokio::spawn(async move {
+            let options = WatchOptions {
+                timeout: Some(Duration::from_secs(24 * 60 * 60)),
+                // 编辑后的第一次查询可能仍是编辑前已开放浏览的状态
+                grace: Duration::from_secs(10 * 60),
+                ..Default::default()
+            };
+            let result = bili
+                .watch(&vid, &options, |state, _| {
+                    info!("{vid} {title}: {state}");
+                    progress.on_event(UploadEvent::ReviewStateChanged {
+                        vid: vid.to_string(),
+                        title: title.clone(),
+                        state: state.clone(),
+                    });
+                })
+                .await;
+            if let Err(e) = result {
+                warn!("failed to watch {vid}: {e}");
+            }
+        }));
+    }
+
+    /// 未指定线路时使用缓存的测速结果，过期后重新测速
+    async fn line(&self) -> Failover {
+        let line: Failover = match self.settings.line.clone() {
+            Some(line) => line.into(),
+            None => Failover::new(
+                Probe::rank_cached(
+                    &self.settings.client.client,
+                    &Probe::cache_file(),
+                    PROBE_CACHE_TTL,
+                )
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("failed to probe upload lines: {e}");
+                    Vec::new()
+                }),
+            ),
+        };
+        line.resume(true)
+    }
+
     async fn upload(
         &self,
-        video_paths: &[&Path],
+        video_files: Vec<VideoFile>,
         bili: &BiliBili,
-        line: Line,
+        line: Failover,
         limit: usize,
     ) -> crate::error::Result<Vec<Video>> {
-        let mut videos = Vec::new();
-        for video_path in video_paths {
-            println!("{:?}", video_path.canonicalize()?.to_str());
-            info!("{line:?}");
-            let video_file = VideoFile::new(video_path)?;
-            let total_size = video_file.total_size;
-            let file_name = video_file.file_name.clone();
-            let uploader = line.pre_upload(bili, video_file).await?;
-
-            let instant = Instant::now();
-
-            let video = uploader
-                .upload(self.client.clone(), limit, |vs| {
-                    vs.map(|vs| {
-                        let chunk = vs?;
-                        let len = chunk.len();
-                        Ok((chunk, len))
-                    })
-                })
-                .await?;
-            let t = instant.elapsed().as_millis();
-            info!(
-                "Upload completed: {file_name} => cost {:.2}s, {:.2} MB/s.",
-                t as f64 / 1000.,
-                total_size as f64 / 1000. / t as f64
-            );
-            videos.push(video);
+        let files = self.settings.files.max(1);
+        let chunk_limiter = self
+            .settings
+            .chunk_limiter
+            .clone()
+            .unwrap_or_else(|| ChunkLimiter::new(limit * files));
+        // 先收集为 BoxFuture，避免 tokio::spawn 时闭包生命周期推断出错
+        let uploads: Vec<_> = video_files
+            .into_iter()
+            .map(|video_file| {
+                self.upload_file(video_file, bili, &line, limit, &chunk_limiter)
+                    .boxed()
+            })
+            .collect();
+        // buffered 按输入顺序返回结果，保证分P顺序不变
+        let videos: Vec<_> = futures::stream::iter(uploads)
+            .buffered(files)
+            .try_collect()
+            .await?;
+        Ok(videos.into_iter().flatten().collect())
+    }
+
+    async fn upload_file(
+        &self,
+        video_file: VideoFile,
+        bili: &BiliBili,
+        line: &Failover,
+        limit: usize,
+        chunk_limiter: &ChunkLimiter,
+    ) -> crate::error::Result<Option<Video>> {
+        info!("{:?}", line.lines());
+        // 边录边传的分段写入结束后才知道大小，另外打开一份用于检查
+        let growing = video_file
+            .finished
+            .is_some()
+            .then(|| video_file.reopen())
+            .flatten()
+            .transpose()?;
+        let total_size = video_file.total_size;
+        let file_name = video_file.file_name.clone();
+
+        let instant = Instant::now();
+
+        let video = line
+            .upload(
+                bili,
+                self.settings.client.clone(),
+                video_file,
+                limit,
+                self.settings.rate_limiter.clone(),
+                Some(chunk_limiter),
+                &self.settings.progress,
+            )
+            .await?;
+        let t = instant.elapsed().as_millis();
+        // 边录边传的分段开始上传时大小为 0，按写入结束后的大小计算速度
+        let size = match &growing {
+            Some(growing) => growing.current_size()?,
+            None => total_size,
+        };
+        info!(
+            "Upload completed: {file_name} => cost {:.2}s, {:.2} MB/s, md5: {}.",
+            t as f64 / 1000.,
+            size as f64 / 1000. / t as f64,
+            video.md5.as_deref().unwrap_or_default()
+        );
+        if growing.is_some() && size <= MIN_FILE_SIZE {
+            info!("{file_name} 只有 {size} 字节，不加入稿件");
+            return Ok(None);
         }
-        Ok(videos)
+        Ok(Some(video))
     }
 
     async fn handle_message(&mut self, msg: ActorMessage) -> crate::error::Result<()> {
         match msg {
-            ActorMessage::Upload { path } => {
-                let bili = login_by_cookies("cookies.json").await?;
-                let videos = self
-                    .upload(&[path.as_path()], &bili, Default::default(), 3)
-                    .await?;
+            ActorMessage::Upload { files } => {
+                let bili =
+                    login_by_cookies_with("cookies.json", (&self.settings.client).into()).await?;
+                let line = self.line().await;
+                let videos = self.upload(files, &bili, line, 3).await?;
+                if videos.is_empty() {
+                    return Ok(());
+                }
 
                 if let Some(vid) = &self.vid {
                     let mut studio = bili.studio_data(vid).await?;
                     studio.videos.extend(videos);
-                    bili.edit(&studio).await?;
+                    let result = self
+                        .settings
+                        .scheduler
+                        .edit(&bili, &studio, self.settings.submit)
+                        .await;
+                    self.settings
+                        .progress
+                        .on_event(UploadEvent::submitted(&studio.title, &result));
+                    if result.is_err() {
+                        save_studio(&studio);
+                    }
+                    result?;
+                    self.watch_review(bili, vid.clone(), studio.title);
                 } else {
                     let studio = &mut self.studio;
                     studio.videos.extend(videos);
                     if studio.title.is_empty() {
-                        studio.title = path
-                            .file_stem()
-                            .and_then(|fname| fname.to_str())
-                            .unwrap_or_default()
-                            .to_string();
+                        studio.title = studio.videos[0].title.clone().unwrap_or_default();
                     }
                     if studio.tag.is_empty() {
                         studio.tag = bili
@@ -105,16 +277,33 @@ impl UploadActor {
                             .unwrap_or("录播")
                             .to_string();
                     }
-                    let result = bili.submit(studio).await?;
-                    self.vid = Some(
-                        result
-                            .data
-                            .as_ref()
-                            .and_then(|data| data.get("bvid"))
-                            .and_then(|vid| vid.as_str())
-                            .map(|vid| Vid::Bvid(String::from(vid)))
-                            .ok_or_else(|| Kind::Custom(format!("{:?}", result)))?,
-                    );
+                    // 重试用尽后已上传的分P仍保留在 studio 中，随下一个分段一起提交
+                    let result = self
+                        .settings
+                        .scheduler
+                        .submit(&bili, studio, self.settings.submit)
+                        .await;
+                    self.settings
+                        .progress
+                        .on_event(UploadEvent::submitted(&studio.title, &result));
+                    let result = match result {
+                        Ok(result) => result,
+                        Err(e) => {
+                            self.save_unsubmitted();
+                            return Err(e);
+                        }
+                    };
+                    self.remove_unsubmitted();
+                    let vid = result.vid().ok_or_else(|| result.to_error())?;
+                    self.vid = Some(vid.clone());
+                    if let Some(season_id) = self.studio.season_id {
+                        // 加入合集失败不影响已提交的稿件
+                        if let Err(e) = bili.add_to_season(season_id, &vid).await {
+                            warn!("failed to add {vid} to season {season_id}: {e}");
+                        }
+                    }
+                    let title = self.studio.title.clone();
+                    self.watch_review(bili, vid, title);
                 }
             }
         }
@@ -122,10 +311,42 @@ impl UploadActor {
     }
 }
 
+/// 保存到当前目录，与命令行投稿失败时相同
+fn save_studio(studio: &Studio) -> Option<PathBuf> {
+    match save_unsubmitted(Path::new("."), studio) {
+        Ok(path) => {
+            warn!(
+                "{} 未能提交，投稿信息已保存到 {}",
+                studio.title,
+                path.display()
+            );
+            Some(path)
+        }
+        Err(e) => {
+            error!("failed to save unsubmitted studio {}: {e}", studio.title);
+            None
+        }
+    }
+}
+
 async fn run_download_actor(mut actor: UploadActor) {
-    while let Some(msg) = actor.receiver.recv().await {
+    while let Some(mut msg) = actor.receiver.recv().await {
+        // 上传跟不上录制时，把排队中的分段合并到一起并发上传
+        while let Ok(ActorMessage::Upload { files: queued }) = actor.receiver.try_recv() {
+            let ActorMessage::Upload { files } = &mut msg;
+            files.extend(queued);
+        }
         match actor.handle_message(msg).await {
             Ok(_) => {}
+            Err(Kind::BiliApi(e)) if e.needs_relogin() => {
+                error!("{e}, cookies.json 登录信息已失效，请重新登录")
+            }
+            Err(Kind::BiliApi(e)) if e.is_title_too_long() => {
+                error!("{e}, 请缩短投稿模板中的标题")
+            }
+            Err(Kind::BiliApi(e)) if e.is_duplicate() => {
+                warn!("{e}, 稿件已提交过，不再重复提交")
+            }
             Err(e) => {
                 error!("{}", e)
             }
@@ -139,18 +360,33 @@ pub struct UploadActorHandle {
 }
 
 impl UploadActorHandle {
-    pub fn new(client: StatelessClient, studio: Studio) -> Self {
+    pub fn new(studio: Studio, settings: UploadSettings) -> Self {
         let (sender, receiver) = mpsc::unbounded_channel();
-        let actor = UploadActor::new(studio, client, receiver);
+        let actor = UploadActor::new(studio, settings, receiver);
         tokio::spawn(run_download_actor(actor));
 
         Self { sender }
     }
 
     pub fn send_file_path<T: AsRef<Path>>(&self, path: T) {
-        let msg = ActorMessage::Upload {
-            path: PathBuf::from(path.as_ref()),
-        };
-        let _ = self.sender.send(msg);
+        self.send(VideoFile::new(path.as_ref()), path.as_ref());
+    }
+
+    /// 上传仍在写入的文件，`finished` 发出信号后完成上传。
+    /// 文件在此处立即打开，写入结束后被重命名也能继续读取
+    pub fn send_growing_file<T: AsRef<Path>>(&self, path: T, finished: FinishSignal) {
+        self.send(VideoFile::follow(path.as_ref(), finished), path.as_ref());
+    }
+
+    fn send(&self, video_file: std::io::Result<VideoFile>, path: &Path) {
+        match video_file {
+            Ok(video_file) => {
+                let msg = ActorMessage::Upload {
+                    files: vec![video_file],
+                };
+                let _ = self.sender.send(msg);
+            }
+            Err(e) => error!("unable to open {}: {e}", path.display()),
+        }
     }
 }
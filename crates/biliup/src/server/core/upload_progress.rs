@@ -0,0 +1,209 @@
+use crate::uploader::observer::{UploadEvent, UploadObserver};
+use indexmap::IndexMap;
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// 所有上传任务的进度，供 `/v1/upload/progress` 查询，事件同时转发给 `/v1/upload/events` 的订阅者
+#[derive(Clone, Debug)]
+pub struct UploadProgress {
+    state: Arc<Mutex<ProgressState>>,
+    sender: broadcast::Sender<UploadEvent>,
+}
+
+#[derive(Debug, Default)]
+struct ProgressState {
+    /// 以完整路径区分不同目录下的同名文件
+    files: IndexMap<String, FileProgress>,
+    last_submission: Option<UploadEvent>,
+    reviews: IndexMap<String, UploadEvent>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, PartialEq)]
+pub struct FileProgress {
+    pub file_name: String,
+    pub path: String,
+    /// 边录边传时为空
+    pub total_size: Option<u64>,
+    pub uploaded: u64,
+    pub retries: usize,
+    pub completed: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProgressSnapshot {
+    pub files: Vec<FileProgress>,
+    pub last_submission: Option<UploadEvent>,
+    /// 已提交稿件最近一次的审核状态变化
+    pub reviews: Vec<UploadEvent>,
+}
+
+impl UploadProgress {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(256);
+        Self {
+            state: Default::default(),
+            sender,
+        }
+    }
+
+    pub fn snapshot(&self) -> ProgressSnapshot {
+        let state = self.state.lock().unwrap();
+        ProgressSnapshot {
+            files: state.files.values().cloned().collect(),
+            last_submission: state.last_submission.clone(),
+            reviews: state.reviews.values().cloned().collect(),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<UploadEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for UploadProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UploadObserver for UploadProgress {
+    fn on_event(&self, event: UploadEvent) {
+        {
+            let mut state = self.state.lock().unwrap();
+            match &event {
+                UploadEvent::PreUpload {
+                    file_name,
+                    path,
+                    total_size,
+                } => {
+                    // 切换线路后重新计算
+                    state.files.insert(
+                        path.clone(),
+                        FileProgress {
+                            file_name: file_name.clone(),
+                            path: path.clone(),
+                            total_size: *total_size,
+                            ..Default::default()
+                        },
+                    );
+                }
+                UploadEvent::ChunkFinished { path, size, .. } => {
+                    if let Some(file) = state.files.get_mut(path) {
+                        file.uploaded += *size as u64;
+                    }
+                }
+                UploadEvent::ChunkRetried { path, .. } => {
+                    if let Some(file) = state.files.get_mut(path) {
+                        file.retries += 1;
+                    }
+                }
+                UploadEvent::FileCompleted { path, .. } => {
+                    if let Some(file) = state.files.get_mut(path) {
+                        file.completed = true;
+                    }
+                }
+                UploadEvent::Submitted { .. } => {
+                    // 已完成的文件随稿件一起提交，不再显示
+                    state.files.retain(|_, file| !file.completed);
+                    state.last_submission = Some(event.clone());
+                }
+                UploadEvent::ReviewStateChanged { vid, .. } => {
+                    state.reviews.insert(vid.clone(), event.clone());
+                }
+                UploadEvent::ChunkStarted { .. } => {}
+            }
+        }
+        // 没有订阅者时发送失败，忽略即可
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::uploader::archive::ReviewState;
+
+    #[test]
+    fn progress_follows_events() {
+        let progress = UploadProgress::new();
+        let mut events = progress.subscribe();
+        for path in ["a/a.flv", "b/a.flv"] {
+            progress.on_event(UploadEvent::PreUpload {
+                file_name: "a.flv".into(),
+                path: path.into(),
+                total_size: Some(20),
+            });
+        }
+        progress.on_event(UploadEvent::ChunkFinished {
+            file_name: "a.flv".into(),
+            path: "a/a.flv".into(),
+            index: 0,
+            size: 10,
+        });
+        progress.on_event(UploadEvent::ChunkRetried {
+            file_name: "a.flv".into(),
+            path: "a/a.flv".into(),
+            index: 1,
+            attempt: 1,
+            error: "timeout".into(),
+        });
+        assert_eq!(
+            progress.snapshot().files,
+            [
+                FileProgress {
+                    file_name: "a.flv".into(),
+                    path: "a/a.flv".into(),
+                    total_size: Some(20),
+                    uploaded: 10,
+                    retries: 1,
+                    completed: false,
+                },
+                FileProgress {
+                    file_name: "a.flv".into(),
+                    path: "b/a.flv".into(),
+                    total_size: Some(20),
+                    ..Default::default()
+                }
+            ]
+        );
+        assert!(matches!(
+            events.try_recv(),
+            Ok(UploadEvent::PreUpload { .. })
+        ));
+
+        progress.on_event(UploadEvent::FileCompleted {
+            file_name: "a.flv".into(),
+            path: "a/a.flv".into(),
+            filename: "n230101".into(),
+            md5: None,
+        });
+        progress.on_event(UploadEvent::Submitted {
+            title: "a".into(),
+            data: None,
+            error: None,
+        });
+        let snapshot = progress.snapshot();
+        assert_eq!(snapshot.files.len(), 1);
+        assert_eq!(snapshot.files[0].path, "b/a.flv");
+        assert!(snapshot.last_submission.is_some());
+
+        progress.on_event(UploadEvent::ReviewStateChanged {
+            vid: "BV1xx".into(),
+            title: "a".into(),
+            state: ReviewState::Reviewing,
+        });
+        progress.on_event(UploadEvent::ReviewStateChanged {
+            vid: "BV1xx".into(),
+            title: "a".into(),
+            state: ReviewState::Published,
+        });
+        assert!(matches!(
+            progress.snapshot().reviews[..],
+            [UploadEvent::ReviewStateChanged {
+                state: ReviewState::Published,
+                ..
+            }]
+        ));
+    }
+}
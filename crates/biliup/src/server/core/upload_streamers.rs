@@ -50,6 +50,9 @@ pub struct StudioEntity {
     pub up_selection_reply: bool,
     pub up_close_reply: bool,
     pub up_close_danmu: bool,
+    /// 投稿成功后加入的合集
+    #[serde(default)]
+    pub season_id: Option<u32>,
 }
 
 impl StudioEntity {
@@ -69,6 +72,8 @@ impl StudioEntity {
             desc_v2: None,
             dtime: self.dtime,
             open_subtitle: false,
+            // 录播分段没有字幕
+            subtitles: vec![],
             interactive: self.interactive,
             mission_id: self.mission_id,
             dolby: self.dolby,
@@ -79,6 +84,8 @@ impl StudioEntity {
             up_selection_reply: self.up_selection_reply,
             up_close_reply: self.up_close_reply,
             up_close_danmu: self.up_close_danmu,
+            season_id: self.season_id,
+            extra_fields: None,
         }
     }
 
@@ -123,6 +130,7 @@ impl Default for StudioEntity {
             up_selection_reply: false,
             up_close_reply: false,
             up_close_danmu: false,
+            season_id: None,
         }
     }
 }
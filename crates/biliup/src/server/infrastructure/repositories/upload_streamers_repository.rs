@@ -41,9 +41,10 @@ impl UploadStreamersRepository for SqliteUploadStreamersRepository {
                 open_elec,
                 up_selection_reply,
                 up_close_reply,
-                up_close_danmu
+                up_close_danmu,
+                season_id
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21)
             returning
                 id,
                 template_name as "template_name!",
@@ -65,7 +66,8 @@ impl UploadStreamersRepository for SqliteUploadStreamersRepository {
                 open_elec as "open_elec!: u8",
                 up_selection_reply as "up_selection_reply!: bool",
                 up_close_reply as "up_close_reply!: bool",
-                up_close_danmu as "up_close_danmu!: bool""#,
+                up_close_danmu as "up_close_danmu!: bool",
+                season_id as "season_id: u32""#,
             studio.template_name,
             studio.user,
             studio.copyright,
@@ -86,6 +88,7 @@ impl UploadStreamersRepository for SqliteUploadStreamersRepository {
             studio.up_selection_reply,
             studio.up_close_reply,
             studio.up_close_danmu,
+            studio.season_id,
         )
         .fetch_one(&self.pool)
         .await
@@ -123,7 +126,8 @@ impl UploadStreamersRepository for SqliteUploadStreamersRepository {
             open_elec          as "open_elec!: u8",
             up_selection_reply as "up_selection_reply!: bool",
             up_close_reply     as "up_close_reply!: bool",
-            up_close_danmu     as "up_close_danmu!: bool"
+            up_close_danmu     as "up_close_danmu!: bool",
+            season_id          as "season_id: u32"
      from upload_streamers"#
         )
         .fetch_all(&self.pool)
@@ -136,7 +140,7 @@ impl UploadStreamersRepository for SqliteUploadStreamersRepository {
             StudioEntity,
             r#"
        select
-            id, template_name as "template_name!", user, copyright as "copyright!: u8", source as "source!", tid as "tid!: u16", cover as "cover!", title as "title!", desc as "desc!", dynamic as "dynamic!", tag as "tag!", dtime as "dtime: u32", interactive as "interactive!: u8", mission_id as "mission_id: u32", dolby as "dolby!: u8", lossless_music as "lossless_music!: u8", no_reprint as "no_reprint!: u8", open_elec as "open_elec!: u8", up_selection_reply as "up_selection_reply!: bool", up_close_reply as "up_close_reply!: bool", up_close_danmu as "up_close_danmu!: bool"
+            id, template_name as "template_name!", user, copyright as "copyright!: u8", source as "source!", tid as "tid!: u16", cover as "cover!", title as "title!", desc as "desc!", dynamic as "dynamic!", tag as "tag!", dtime as "dtime: u32", interactive as "interactive!: u8", mission_id as "mission_id: u32", dolby as "dolby!: u8", lossless_music as "lossless_music!: u8", no_reprint as "no_reprint!: u8", open_elec as "open_elec!: u8", up_selection_reply as "up_selection_reply!: bool", up_close_reply as "up_close_reply!: bool", up_close_danmu as "up_close_danmu!: bool", season_id as "season_id: u32"
        from upload_streamers
        where
             id = $1
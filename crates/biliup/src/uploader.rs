@@ -1,27 +1,49 @@
-use crate::uploader::bilibili::Studio;
+use crate::retry_policy::RetryPolicy;
+use crate::uploader::bilibili::{Studio, SubmitOption};
+use crate::uploader::category::{ArchivePre, Tid};
+use crate::uploader::line::Line;
+use crate::uploader::source::{HttpSource, UploadSource};
 use crate::{error, Stream};
 use bytes::Bytes;
+use futures::future::poll_fn;
+use md5::{Digest, Md5};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::io;
 use std::io::{ErrorKind, Read};
-use std::ops::DerefMut;
 use std::path::Path;
 use std::pin::Pin;
-use std::task::{Context, Poll};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
 
+pub mod archive;
 pub mod bilibili;
+pub mod category;
+pub mod chunk_limiter;
 pub mod credential;
+pub mod edit;
 pub mod line;
+pub mod observer;
+pub mod rate_limiter;
+pub mod season;
+pub mod source;
+pub mod submit_scheduler;
+pub mod subtitle;
+pub mod validate;
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum Uploader {
     Upos,
-    // Kodo,
+    Kodo,
     // Bos,
     // Gcs,
-    // Cos,
+    Cos,
 }
 
 #[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
@@ -41,6 +63,23 @@ pub struct Config {
     pub line: Option<String>,
     #[serde(default = "default_limit")]
     pub limit: usize,
+    /// 同时上传的文件数
+    #[serde(default = "default_files")]
+    pub files: usize,
+    /// 所有文件同时上传的分块总数上限
+    pub max_chunks: Option<usize>,
+    /// 上传限速，单位为字节每秒，所有文件共享，0 为不限速
+    pub max_upload_rate: Option<u64>,
+    /// 自定义上传线路，可在 `line` 中按名称选择
+    #[serde(default)]
+    pub lines: HashMap<String, Line>,
+    /// 重试策略，命令行参数优先
+    pub retry: Option<RetryPolicy>,
+    /// 投稿接口，可选 client、app 或 web，命令行参数优先
+    pub submit: Option<SubmitOption>,
+    /// 同一账号两次投稿的最小间隔，如 `30s`
+    #[serde(default, with = "crate::retry_policy::humantime_serde::option")]
+    pub submit_interval: Option<Duration>,
     pub streamers: HashMap<String, Studio>,
 }
 
@@ -48,73 +87,273 @@ fn default_limit() -> usize {
     3
 }
 
+fn default_files() -> usize {
+    1
+}
+
 pub fn load_config(config: &Path) -> error::Result<Config> {
+    load_config_with(config, None)
+}
+
+/// 读取配置文件，`streamers` 中以名称填写的分区通过 `archive_pre` 转换为 id
+pub fn load_config_with(config: &Path, archive_pre: Option<&ArchivePre>) -> error::Result<Config> {
     let file = std::fs::File::open(config)?;
-    let config: Config = serde_yaml::from_reader(file)?;
+    let mut config: serde_yaml::Value = serde_yaml::from_reader(file)?;
+    resolve_tids(&mut config, archive_pre)?;
+    let config: Config = serde_yaml::from_value(config)?;
+    if config.limit == 0 || config.files == 0 || config.max_chunks == Some(0) {
+        return Err(error::Kind::Custom(
+            "limit, files and max_chunks must be greater than 0".into(),
+        ));
+    }
     // println!("body = {:?}", client);
     Ok(config)
 }
 
+fn resolve_tids(
+    config: &mut serde_yaml::Value,
+    archive_pre: Option<&ArchivePre>,
+) -> error::Result<()> {
+    let Some(streamers) = config
+        .get_mut("streamers")
+        .and_then(serde_yaml::Value::as_mapping_mut)
+    else {
+        return Ok(());
+    };
+    for (pattern, studio) in streamers.iter_mut() {
+        let Some(tid) = studio.get_mut("tid") else {
+            continue;
+        };
+        let Some(name) = tid.as_str() else {
+            continue;
+        };
+        let id = name
+            .parse::<Tid>()
+            .map_err(error::Kind::Custom)?
+            .resolve(archive_pre)
+            .map_err(|e| {
+                error::Kind::Custom(format!("{}: {e}", pattern.as_str().unwrap_or_default()))
+            })?;
+        *tid = id.into();
+    }
+    Ok(())
+}
+
+/// 读取 YAML 文件中 `lines` 下的自定义上传线路，投稿配置文件也可以直接使用
+pub fn load_lines(path: &Path) -> error::Result<HashMap<String, Line>> {
+    #[derive(Deserialize)]
+    struct Lines {
+        #[serde(default)]
+        lines: HashMap<String, Line>,
+    }
+    let file = std::fs::File::open(path)?;
+    let Lines { lines } = serde_yaml::from_reader(file)?;
+    Ok(lines)
+}
+
+/// 读取视频时计算的各分块及整个文件的 MD5，克隆后共享
+#[derive(Clone, Default)]
+pub struct Digests(Arc<Mutex<DigestsInner>>);
+
+#[derive(Default)]
+struct DigestsInner {
+    chunks: Vec<String>,
+    file: Md5,
+}
+
+impl Digests {
+    fn update(&self, chunk: &[u8]) {
+        let mut inner = self.0.lock().unwrap();
+        inner.chunks.push(format!("{:x}", Md5::digest(chunk)));
+        inner.file.update(chunk);
+    }
+
+    /// 第 `index` 个分块的 MD5，尚未读到时为空
+    pub fn chunk(&self, index: usize) -> Option<String> {
+        self.0.lock().unwrap().chunks.get(index).cloned()
+    }
+
+    /// 已读取内容的 MD5，读完后即为整个文件的 MD5
+    pub fn file(&self) -> String {
+        format!("{:x}", self.0.lock().unwrap().file.clone().finalize())
+    }
+}
+
+/// 文件写入结束的信号，用于边录边传，克隆后共享
+#[derive(Clone, Debug, Default)]
+pub struct FinishSignal(Arc<AtomicBool>);
+
+impl FinishSignal {
+    pub fn finish(&self) {
+        self.0.store(true, Ordering::Release)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+}
+
+/// 追踪仍在写入的文件时，读到末尾后等待新数据的间隔
+const FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+
+enum Reader {
+    File(std::fs::File),
+    Source(Box<dyn UploadSource>),
+}
+
+/// 读取分块的流，读取和计算 MD5 都在阻塞线程池中进行并预读一个分块，
+/// 避免磁盘较慢时卡住运行时
 pub struct VideoStream {
     pub capacity: usize,
-    buffer: Vec<u8>,
-    pub file: std::fs::File,
+    reader: Option<Reader>,
+    digests: Digests,
+    finished: Option<FinishSignal>,
+    receiver: Option<mpsc::Receiver<io::Result<Bytes>>>,
 }
 
 impl VideoStream {
     pub fn with_capacity(file: std::fs::File, capacity: usize) -> Self {
-        // self.capacity = capacity;
-        // self.buffer = vec![0u8; capacity];
-        // self.buf = BytesMut::with_capacity(capacity);
+        Self::new(Reader::File(file), capacity)
+    }
+
+    pub fn from_source(source: impl UploadSource, capacity: usize) -> Self {
+        Self::new(Reader::Source(Box::new(source)), capacity)
+    }
+
+    fn new(reader: Reader, capacity: usize) -> Self {
         VideoStream {
             capacity,
-            buffer: vec![0u8; capacity],
-            file,
+            reader: Some(reader),
+            digests: Digests::default(),
+            finished: None,
+            receiver: None,
         }
     }
 
-    pub fn read(&mut self) -> io::Result<Option<Bytes>> {
-        let mut len = 0;
-        let mut buf = self.buffer.deref_mut();
-        while !buf.is_empty() {
-            match self.file.read(buf) {
-                Ok(0) => break,
-                Ok(n) => {
-                    let tmp = buf;
-                    len += n;
-                    buf = &mut tmp[n..];
-                }
-                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
-                Err(e) => return Err(e),
-            }
-        }
-        if len == 0 {
-            Ok(None)
-        } else {
-            Ok(Some(Bytes::copy_from_slice(&self.buffer[..len])))
-        }
+    /// 追踪仍在写入的文件，读到末尾时等待新数据，直到 `finished` 发出信号
+    pub fn follow(mut self, finished: FinishSignal) -> Self {
+        self.finished = Some(finished);
+        self
+    }
+
+    pub fn digests(&self) -> Digests {
+        self.digests.clone()
     }
 }
 
 impl Stream for VideoStream {
     type Item = io::Result<Bytes>;
 
-    fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        match self.read()? {
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        // 首次拉取时才开始读取，此时一定处于 tokio 运行时中
+        if let Some(reader) = this.reader.take() {
+            let (tx, rx) = mpsc::channel(1);
+            let chunks = ChunkReader {
+                reader,
+                handle: Handle::current(),
+                buffer: vec![0u8; this.capacity],
+                filled: 0,
+                digests: this.digests.clone(),
+                finished: this.finished.take(),
+            };
+            tokio::task::spawn_blocking(move || chunks.run(tx));
+            this.receiver = Some(rx);
+        }
+        match &mut this.receiver {
+            Some(receiver) => receiver.poll_recv(cx),
             None => Poll::Ready(None),
-            Some(b) => Poll::Ready(Some(Ok(b))),
         }
     }
 }
 
+struct ChunkReader {
+    reader: Reader,
+    handle: Handle,
+    buffer: Vec<u8>,
+    filled: usize,
+    digests: Digests,
+    finished: Option<FinishSignal>,
+}
+
+impl ChunkReader {
+    /// 在阻塞线程中运行，直到读完或上传被取消
+    fn run(mut self, tx: mpsc::Sender<io::Result<Bytes>>) {
+        loop {
+            // 先取信号再读，保证信号发出前写入的数据都能读到
+            let finished = self.finished.as_ref().is_none_or(FinishSignal::is_finished);
+            let eof = match self.fill() {
+                Ok(eof) => eof,
+                Err(e) => {
+                    let _ = tx.blocking_send(Err(e));
+                    return;
+                }
+            };
+            if !eof || finished {
+                let Some(chunk) = self.take() else {
+                    return;
+                };
+                if tx.blocking_send(Ok(chunk)).is_err() {
+                    return;
+                }
+            } else if tx.is_closed() {
+                return;
+            } else {
+                std::thread::sleep(FOLLOW_INTERVAL);
+            }
+        }
+    }
+
+    /// 填充缓冲区，返回是否读到了末尾
+    fn fill(&mut self) -> io::Result<bool> {
+        while self.filled < self.buffer.len() {
+            let buf = &mut self.buffer[self.filled..];
+            let read = match &mut self.reader {
+                Reader::File(file) => file.read(buf),
+                Reader::Source(source) => self.handle.block_on(poll_fn(|cx| {
+                    let mut buf = ReadBuf::new(buf);
+                    ready!(Pin::new(&mut *source).poll_read(cx, &mut buf))?;
+                    Poll::Ready(Ok(buf.filled().len()))
+                })),
+            };
+            match read {
+                Ok(0) => return Ok(true),
+                Ok(n) => self.filled += n,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(false)
+    }
+
+    fn take(&mut self) -> Option<Bytes> {
+        if self.filled == 0 {
+            return None;
+        }
+        let capacity = self.buffer.len();
+        let mut chunk = std::mem::replace(&mut self.buffer, vec![0u8; capacity]);
+        chunk.truncate(self.filled);
+        self.digests.update(&chunk);
+        self.filled = 0;
+        Some(Bytes::from(chunk))
+    }
+}
+
 pub struct VideoFile {
+    /// 开始上传时的大小，来源长度未知时为 0
     pub total_size: u64,
     pub file_name: String,
     pub filepath: std::path::PathBuf,
-    pub file: std::fs::File,
+    reader: Option<Reader>,
+    size: Option<u64>,
+    /// 文件仍在写入时为写入结束的信号，`total_size` 只是开始上传时的大小
+    pub finished: Option<FinishSignal>,
 }
 
 impl VideoFile {
+    /// 从标准输入上传时的分P文件名
+    pub const STDIN_FILE_NAME: &'static str = "stdin.flv";
+
     pub fn new(filepath: &std::path::Path) -> io::Result<Self> {
         let file = std::fs::File::open(filepath)?;
         let total_size = file.metadata()?.len();
@@ -123,15 +362,214 @@ impl VideoFile {
             .and_then(|file_name| file_name.to_str())
             .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "the path terminates in .."))?;
         Ok(Self {
-            file,
+            reader: Some(Reader::File(file)),
+            size: Some(total_size),
             // capacity: 10485760,
             total_size,
             file_name: file_name.into(),
             filepath: filepath.into(),
+            finished: None,
+        })
+    }
+
+    /// 上传仍在写入的文件，直到 `finished` 发出信号
+    pub fn follow(filepath: &std::path::Path, finished: FinishSignal) -> io::Result<Self> {
+        let mut video_file = Self::new(filepath)?;
+        video_file.finished = Some(finished);
+        Ok(video_file)
+    }
+
+    /// 从标准输入、内存等非本地文件的来源上传，`file_name` 用作分P文件名
+    pub fn from_source(file_name: &str, source: impl UploadSource) -> Self {
+        let size = source.size();
+        Self {
+            total_size: size.unwrap_or_default(),
+            file_name: file_name.into(),
+            filepath: file_name.into(),
+            reader: Some(Reader::Source(Box::new(source))),
+            size,
+            finished: None,
+        }
+    }
+
+    /// 上传 HTTP 地址上的视频，文件名取自地址的最后一段
+    pub async fn from_url(client: &reqwest::Client, url: &str) -> error::Result<Self> {
+        let response = client.get(url).send().await?.error_for_status()?;
+        let file_name = response
+            .url()
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .unwrap_or("video")
+            .to_string();
+        Ok(Self::from_source(&file_name, HttpSource::new(response)))
+    }
+
+    /// 未填写标题时分P与稿件使用的默认标题，即不含扩展名的文件名
+    pub fn default_title(filepath: &Path) -> Option<String> {
+        filepath
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(Into::into)
+    }
+
+    /// 已知且不会再变化的大小
+    pub fn size(&self) -> Option<u64> {
+        self.size.filter(|_| self.finished.is_none())
+    }
+
+    /// 重新打开本地文件以便从头再读一次，其他来源无法重新读取。
+    /// 写入结束的 `.part` 文件会被重命名，此时打开去掉 `.part` 后的文件
+    pub fn reopen(&self) -> Option<io::Result<Self>> {
+        let Some(Reader::File(_)) = self.reader else {
+            return None;
+        };
+        let video_file = match Self::new(&self.filepath) {
+            Err(e)
+                if e.kind() == ErrorKind::NotFound
+                    && self.filepath.extension().is_some_and(|ext| ext == "part") =>
+            {
+                Self::new(&self.filepath.with_extension(""))
+            }
+            result => result,
+        };
+        Some(video_file.map(|mut video_file| {
+            video_file.finished = self.finished.clone();
+            video_file
+        }))
+    }
+
+    /// 本地文件当前的大小，仍在写入的文件写入结束后即为最终大小
+    pub fn current_size(&self) -> io::Result<u64> {
+        match &self.reader {
+            Some(Reader::File(file)) => Ok(file.metadata()?.len()),
+            _ => Ok(self.total_size),
+        }
+    }
+
+    /// 只有写入完成的本地文件才能断点续传
+    pub fn is_resumable(&self) -> bool {
+        matches!(self.reader, Some(Reader::File(_))) && self.finished.is_none()
+    }
+
+    /// 本地文件可多次读取，其他来源只能读取一次
+    pub fn get_stream(&mut self, capacity: usize) -> io::Result<VideoStream> {
+        let stream = match self.reader.take() {
+            Some(Reader::File(file)) => {
+                let stream = VideoStream::with_capacity(file.try_clone()?, capacity);
+                self.reader = Some(Reader::File(file));
+                stream
+            }
+            Some(reader) => VideoStream::new(reader, capacity),
+            None => {
+                return Err(io::Error::new(
+                    ErrorKind::UnexpectedEof,
+                    format!("{} has already been consumed", self.file_name),
+                ))
+            }
+        };
+        Ok(match &self.finished {
+            Some(finished) => stream.follow(finished.clone()),
+            None => stream,
         })
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use std::io::Write;
+
+    #[tokio::test]
+    async fn video_stream_follows_growing_file() -> io::Result<()> {
+        let path = std::env::temp_dir().join("biliup-follow-test.flv.part");
+        std::fs::write(&path, [0u8; 1500])?;
+        let finished = FinishSignal::default();
+        let stream = VideoFile::follow(&path, finished.clone())?.get_stream(1000)?;
+        let writer = {
+            let path = path.clone();
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let mut file = std::fs::OpenOptions::new().append(true).open(path)?;
+                file.write_all(&[1u8; 1000])?;
+                finished.finish();
+                Ok::<_, io::Error>(())
+            })
+        };
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        writer.await??;
+        let lens: Vec<_> = chunks.iter().map(Bytes::len).collect();
+        assert_eq!(lens, [1000, 1000, 500]);
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn reopen_finds_renamed_part_file() -> io::Result<()> {
+        let part = std::env::temp_dir().join("biliup-reopen-test.flv.part");
+        std::fs::write(&part, [0u8; 100])?;
+        let video_file = VideoFile::follow(&part, FinishSignal::default())?;
+        let renamed = part.with_extension("");
+        std::fs::rename(&part, &renamed)?;
+        let reopened = video_file.reopen().unwrap()?;
+        assert_eq!(reopened.filepath, renamed);
+        assert!(reopened.finished.is_some());
+        std::fs::remove_file(renamed)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn video_file_from_source_reads_once() -> io::Result<()> {
+        let mut video_file = VideoFile::from_source("memory.flv", io::Cursor::new(vec![7u8; 2500]));
+        assert_eq!(video_file.size(), Some(2500));
+        assert!(!video_file.is_resumable());
+        let stream = video_file.get_stream(1000)?;
+        let digests = stream.digests();
+        let chunks: Vec<Bytes> = stream.try_collect().await?;
+        let lens: Vec<_> = chunks.iter().map(Bytes::len).collect();
+        assert_eq!(lens, [1000, 1000, 500]);
+        assert_eq!(digests.file(), format!("{:x}", Md5::digest([7u8; 2500])));
+        assert!(video_file.get_stream(1000).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn video_file_from_url() -> error::Result<()> {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/bucket/record.flv"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(vec![1u8; 1500]))
+            .mount(&server)
+            .await;
+        let url = format!("{}/bucket/record.flv", server.uri());
+        let mut video_file = VideoFile::from_url(&reqwest::Client::new(), &url).await?;
+        assert_eq!(video_file.file_name, "record.flv");
+        assert_eq!(video_file.size(), Some(1500));
+        let chunks: Vec<Bytes> = video_file.get_stream(1000)?.try_collect().await?;
+        assert_eq!(chunks.concat(), vec![1u8; 1500]);
+        Ok(())
+    }
+
+    #[test]
+    fn resolves_tid_names_in_config() -> error::Result<()> {
+        let archive_pre: ArchivePre = serde_json::from_value(serde_json::json!({"typelist": [
+            {"id": 4, "name": "游戏", "children": [{"id": 17, "parent": 4, "name": "单机游戏"}]}
+        ]}))?;
+        let yaml = "streamers:\n  a*.flv:\n    tid: 单机游戏\n  b*.flv:\n    tid: 171\n";
+        let mut config: serde_yaml::Value = serde_yaml::from_str(yaml)?;
+        resolve_tids(&mut config, Some(&archive_pre))?;
+        assert_eq!(config["streamers"]["a*.flv"]["tid"], 17);
+        assert_eq!(config["streamers"]["b*.flv"]["tid"], 171);
 
-    pub fn get_stream(&self, capacity: usize) -> io::Result<VideoStream> {
-        Ok(VideoStream::with_capacity(self.file.try_clone()?, capacity))
+        let mut config: serde_yaml::Value = serde_yaml::from_str(yaml)?;
+        assert!(resolve_tids(&mut config, None)
+            .unwrap_err()
+            .to_string()
+            .starts_with("a*.flv: "));
+        Ok(())
     }
 }
@@ -0,0 +1,306 @@
+use crate::uploader::season::nullable;
+use chrono::{Local, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// 稿件详情，对应 `x/client/archive/view` 的返回
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ArchiveView {
+    pub archive: ArchiveDetail,
+    #[serde(deserialize_with = "nullable")]
+    pub videos: Vec<ArchiveVideo>,
+    #[serde(alias = "staffs", deserialize_with = "nullable")]
+    pub staff: Vec<Staff>,
+    /// 稿件所属的合集
+    pub season: Option<Season>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ArchiveDetail {
+    pub aid: u64,
+    #[serde(deserialize_with = "nullable")]
+    pub bvid: String,
+    #[serde(deserialize_with = "nullable")]
+    pub title: String,
+    #[serde(deserialize_with = "nullable")]
+    pub cover: String,
+    pub tid: u16,
+    #[serde(deserialize_with = "nullable")]
+    pub tag: String,
+    #[serde(deserialize_with = "nullable")]
+    pub desc: String,
+    /// 1-自制 2-转载
+    pub copyright: u8,
+    #[serde(deserialize_with = "nullable")]
+    pub source: String,
+    /// 0 为开放浏览，负数为审核中或被打回
+    pub state: i16,
+    #[serde(deserialize_with = "nullable")]
+    pub state_desc: String,
+    #[serde(deserialize_with = "nullable")]
+    pub reject_reason: String,
+    /// 单位为秒
+    pub duration: u64,
+    /// 定时发布时间，10位时间戳，未设置时为 0
+    pub dtime: u64,
+    /// 发布时间
+    pub ptime: u64,
+}
+
+/// 分P
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ArchiveVideo {
+    pub cid: u64,
+    #[serde(deserialize_with = "nullable")]
+    pub title: String,
+    #[serde(deserialize_with = "nullable")]
+    pub filename: String,
+    /// 单位为秒
+    pub duration: u64,
+    /// 审核状态，0 为通过
+    pub status: i16,
+    #[serde(deserialize_with = "nullable")]
+    pub status_desc: String,
+    /// 转码状态
+    pub xcode_state: i16,
+    pub fail_code: i32,
+    /// 转码失败的原因
+    #[serde(deserialize_with = "nullable")]
+    pub fail_desc: String,
+    /// 审核未通过的原因
+    #[serde(deserialize_with = "nullable")]
+    pub reject_reason: String,
+}
+
+/// 联合投稿的成员
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Staff {
+    pub mid: u64,
+    #[serde(alias = "uname", deserialize_with = "nullable")]
+    pub name: String,
+    /// 成员的职能，如 UP主、剪辑
+    #[serde(deserialize_with = "nullable")]
+    pub title: String,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Season {
+    #[serde(alias = "id")]
+    pub season_id: u32,
+    #[serde(deserialize_with = "nullable")]
+    pub title: String,
+}
+
+/// 稿件的审核进度，由 [`ArchiveDetail::state`] 推断
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum ReviewState {
+    /// 已提交，正在转码或分发
+    Transcoding,
+    /// 等待审核
+    Reviewing,
+    /// 已通过审核，等待定时发布
+    Scheduled,
+    /// 已开放浏览
+    Published,
+    /// 被退回、锁定或转码失败
+    Rejected { reason: String },
+}
+
+impl ReviewState {
+    /// 不会再变化的状态，轮询到此为止
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            ReviewState::Scheduled | ReviewState::Published | ReviewState::Rejected { .. }
+        )
+    }
+}
+
+impl Display for ReviewState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReviewState::Transcoding => write!(f, "转码中"),
+            ReviewState::Reviewing => write!(f, "审核中"),
+            ReviewState::Scheduled => write!(f, "已通过，等待定时发布"),
+            ReviewState::Published => write!(f, "已开放浏览"),
+            ReviewState::Rejected { reason } => write!(f, "已退回: {reason}"),
+        }
+    }
+}
+
+/// [`BiliBili::watch`](crate::uploader::bilibili::BiliBili::watch) 的轮询间隔，状态不变时间隔翻倍
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    pub interval: Duration,
+    pub max_interval: Duration,
+    /// 超时后返回最后一次查询到的状态，为空时一直等待
+    pub timeout: Option<Duration>,
+    /// 追加分P或编辑后接口可能仍返回之前的最终状态，
+    /// 在此时间内且尚未见到转码、审核等中间状态时不把最终状态当作结果
+    pub grace: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            max_interval: Duration::from_secs(600),
+            timeout: None,
+            grace: Duration::ZERO,
+        }
+    }
+}
+
+impl ArchiveView {
+    /// 状态码参考 <https://github.com/SocialSisterYi/bilibili-API-collect>
+    pub fn review_state(&self) -> ReviewState {
+        let archive = &self.archive;
+        match archive.state {
+            0 | 1 => ReviewState::Published,
+            -40 => ReviewState::Scheduled,
+            -9 | -15 | -20 | -30 => ReviewState::Transcoding,
+            -2 | -3 | -4 | -11 | -12 | -16 | -100 => ReviewState::Rejected {
+                reason: [&archive.reject_reason, &archive.state_desc]
+                    .into_iter()
+                    .find(|reason| !reason.is_empty())
+                    .cloned()
+                    .unwrap_or_else(|| archive.state.to_string()),
+            },
+            _ => ReviewState::Reviewing,
+        }
+    }
+}
+
+/// 可读的稿件摘要，每个分P一行
+impl Display for ArchiveView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let archive = &self.archive;
+        writeln!(f, "{}\tav{}\t{}", archive.bvid, archive.aid, archive.title)?;
+        writeln!(
+            f,
+            "状态: {}\t分区: {}\t时长: {}",
+            status(&archive.state_desc, archive.state, &archive.reject_reason),
+            archive.tid,
+            format_duration(archive.duration)
+        )?;
+        if archive.dtime > 0 {
+            writeln!(f, "定时发布: {}", format_timestamp(archive.dtime))?;
+        } else if archive.ptime > 0 {
+            writeln!(f, "发布时间: {}", format_timestamp(archive.ptime))?;
+        }
+        if !archive.tag.is_empty() {
+            writeln!(f, "标签: {}", archive.tag)?;
+        }
+        if let Some(season) = &self.season {
+            writeln!(f, "合集: {} ({})", season.title, season.season_id)?;
+        }
+        if !self.staff.is_empty() {
+            let staff: Vec<_> = self
+                .staff
+                .iter()
+                .map(|s| format!("{}({}) {}", s.name, s.mid, s.title))
+                .collect();
+            writeln!(f, "合作成员: {}", staff.join(", "))?;
+        }
+        for (i, video) in self.videos.iter().enumerate() {
+            write!(
+                f,
+                "P{}\tcid={}\t{}\t{}\t{}",
+                i + 1,
+                video.cid,
+                format_duration(video.duration),
+                status(&video.status_desc, video.status, &video.reject_reason),
+                video.title
+            )?;
+            if !video.fail_desc.is_empty() {
+                write!(f, "\t转码失败: {}", video.fail_desc)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn status(desc: &str, code: i16, reject_reason: &str) -> String {
+    let desc = if desc.is_empty() {
+        code.to_string()
+    } else {
+        desc.to_string()
+    };
+    if reject_reason.is_empty() {
+        desc
+    } else {
+        format!("{desc}: {reject_reason}")
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+}
+
+/// 以本地时区显示
+fn format_timestamp(secs: u64) -> String {
+    match Local.timestamp_opt(secs as i64, 0).single() {
+        Some(time) => time.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => secs.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summary_lists_parts() {
+        let view: ArchiveView = serde_json::from_value(json!({
+            "archive": {
+                "aid": 170001, "bvid": "BV17x411w7KC", "title": "录播", "tid": 171,
+                "tag": "录播,直播", "state": -2, "state_desc": "已退回",
+                "reject_reason": "分P2有问题", "duration": 3725, "dtime": 0, "ptime": 0,
+                "unknown": "ignored"
+            },
+            "videos": [
+                {"cid": 1, "title": "P1", "duration": 3600, "status": 0, "status_desc": "已通过"},
+                {"cid": 2, "title": "P2", "duration": 125, "status": -2, "status_desc": "已退回",
+                 "reject_reason": "画面问题", "fail_desc": ""}
+            ],
+            "staffs": [{"mid": 2, "uname": "a", "title": "剪辑"}],
+            "season": null
+        }))
+        .unwrap();
+        assert_eq!(view.staff[0].name, "a");
+        assert_eq!(
+            view.review_state(),
+            ReviewState::Rejected {
+                reason: "分P2有问题".into()
+            }
+        );
+        assert!(view.review_state().is_final());
+        assert_eq!(
+            view.to_string(),
+            "BV17x411w7KC\tav170001\t录播\n\
+             状态: 已退回: 分P2有问题\t分区: 171\t时长: 01:02:05\n\
+             标签: 录播,直播\n\
+             合作成员: a(2) 剪辑\n\
+             P1\tcid=1\t01:00:00\t已通过\tP1\n\
+             P2\tcid=2\t00:02:05\t已退回: 画面问题\tP2\n"
+        );
+
+        let view: ArchiveView = serde_json::from_value(json!({
+            "archive": {"bvid": "BV17x411w7KC", "tag": null, "reject_reason": null},
+            "videos": null,
+            "staff": null
+        }))
+        .unwrap();
+        assert!(view.archive.tag.is_empty());
+        assert!(view.videos.is_empty() && view.staff.is_empty());
+    }
+}
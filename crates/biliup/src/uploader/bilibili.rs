@@ -1,5 +1,15 @@
-use crate::error::{Kind, Result};
-use crate::uploader::credential::LoginInfo;
+use crate::client::{proxied, with_retry, StatelessClient};
+use crate::error::{ApiErrorCode, BiliApiError, Kind, Result};
+use crate::retry_policy::{ErrorClass, RetryPolicy, RetryableError};
+use crate::uploader::archive::{ArchiveView, ReviewState, WatchOptions};
+use crate::uploader::category::ArchivePre;
+use crate::uploader::credential::{insert_cookies, LoginInfo};
+use crate::uploader::season::{Episode, Season, Section};
+use crate::uploader::subtitle::{parse_subtitle_file, Bcc, SubtitleFile};
+use reqwest::header::{HeaderMap, HeaderValue, REFERER, USER_AGENT};
+use reqwest::Proxy;
+use reqwest_cookie_store::{CookieStore, CookieStoreMutex};
+use reqwest_middleware::ClientWithMiddleware;
 use serde::ser::Error;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
@@ -8,11 +18,12 @@ use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::num::ParseIntError;
 use std::str::FromStr;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::info;
+use tracing::{info, warn};
 use typed_builder::TypedBuilder;
 
-#[derive(clap::Args, Serialize, Deserialize, Debug, TypedBuilder)]
+#[derive(clap::Args, Serialize, Deserialize, Debug, Clone, TypedBuilder)]
 #[builder(field_defaults(default))]
 pub struct Studio {
     /// 是否转载, 1-自制 2-转载
@@ -24,8 +35,8 @@ pub struct Studio {
     #[clap(long, default_value_t)]
     pub source: String,
 
-    /// 投稿分区
-    #[clap(long, default_value = "171")]
+    /// 投稿分区 id，命令行与配置文件中以名称填写的分区通过 [`Tid`](crate::uploader::category::Tid) 转换
+    #[clap(skip = 171u16)]
     #[builder(default = 171)]
     pub tid: u16,
 
@@ -69,7 +80,7 @@ pub struct Studio {
     #[clap(skip)]
     pub videos: Vec<Video>,
 
-    /// 延时发布时间，距离提交大于4小时，格式为10位时间戳
+    /// 延时发布时间，距离提交大于4小时且不超过15天，格式为10位时间戳
     #[clap(long)]
     pub dtime: Option<u32>,
 
@@ -77,6 +88,12 @@ pub struct Studio {
     #[serde(default)]
     pub open_subtitle: bool,
 
+    // 只在命令行与投稿配置文件中使用，录播服务端不上传字幕
+    /// 分P字幕 [LAN=]PATH，{stem} 为视频文件名，可多次指定
+    #[clap(long = "subtitle", value_parser = parse_subtitle_file)]
+    #[serde(default, skip_serializing)]
+    pub subtitles: Vec<SubtitleFile>,
+
     #[clap(long, default_value = "0")]
     #[serde(default)]
     pub interactive: u8,
@@ -111,6 +128,11 @@ pub struct Studio {
     #[clap(skip)]
     pub aid: Option<u64>,
 
+    /// 投稿成功后加入的合集 id
+    #[clap(long)]
+    #[serde(default, skip_serializing)]
+    pub season_id: Option<u32>,
+
     /// 是否开启精选评论，仅提交接口为app时可用
     #[clap(long)]
     #[serde(default)]
@@ -167,13 +189,13 @@ impl Archive {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct Subtitle {
     open: i8,
     lan: String,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Credit {
     #[serde(rename(deserialize = "type_id", serialize = "type"))]
     pub type_id: i8,
@@ -181,11 +203,17 @@ pub struct Credit {
     pub biz_id: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Video {
     pub title: Option<String>,
     pub filename: String,
     pub desc: String,
+    /// 上传文件的 MD5，不参与投稿
+    #[serde(skip)]
+    pub md5: Option<String>,
+    /// 投稿后上传的字幕，不参与投稿
+    #[serde(skip)]
+    pub subtitles: Vec<SubtitleFile>,
 }
 
 impl Video {
@@ -194,10 +222,25 @@ impl Video {
             title: None,
             filename: filename.into(),
             desc: "".into(),
+            md5: None,
+            subtitles: Vec::new(),
         }
     }
 }
 
+/// 投稿与编辑稿件使用的接口
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SubmitOption {
+    /// 客户端接口，通过 access_key 鉴权
+    #[default]
+    Client,
+    /// APP接口，没有编辑稿件的接口，编辑时使用客户端接口
+    App,
+    /// 网页接口，通过 cookie 与 csrf 鉴权
+    Web,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Vid {
     Aid(u64),
@@ -229,17 +272,127 @@ impl Display for Vid {
     }
 }
 
+#[derive(Clone)]
 pub struct BiliBili {
+    /// 带有登录 cookie 的客户端，所有接口共用
     pub client: reqwest::Client,
+    /// 在 `client` 上加入重试，用于查询等可以安全重试的请求；投稿与编辑不重试，避免重复提交
+    pub client_with_middleware: ClientWithMiddleware,
     pub login_info: LoginInfo,
+    /// 创作中心合集接口的地址
+    creative_url: String,
+}
+
+/// 创作中心合集接口的默认地址
+const CREATIVE_URL: &str = "https://member.bilibili.com/x2/creative/web";
+
+/// 创建 [`BiliBili`] 使用的客户端
+///
+/// ```no_run
+/// # async fn run() -> biliup::error::Result<()> {
+/// use biliup::uploader::bilibili::BiliBili;
+/// use biliup::uploader::credential::login_by_cookies_with;
+/// use std::time::Duration;
+///
+/// let builder = BiliBili::builder()
+///     .timeout(Duration::from_secs(30))
+///     .proxy(Some(reqwest::Proxy::all("socks5h://127.0.0.1:1080")?));
+/// let bili = login_by_cookies_with("cookies.json", builder).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BiliBiliBuilder {
+    user_agent: String,
+    timeout: Duration,
+    pub(crate) proxy: Option<Proxy>,
+    retry_policy: RetryPolicy,
+    creative_url: String,
+}
+
+impl Default for BiliBiliBuilder {
+    fn default() -> Self {
+        Self {
+            user_agent: "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 Chrome/63.0.3239.108"
+                .into(),
+            timeout: Duration::from_secs(60),
+            proxy: None,
+            retry_policy: RetryPolicy::default(),
+            creative_url: CREATIVE_URL.into(),
+        }
+    }
+}
+
+impl BiliBiliBuilder {
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = user_agent.into();
+        self
+    }
+
+    /// 单个请求的超时时间，默认 60 秒
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 登录验证与之后的所有接口都经过此代理
+    pub fn proxy(mut self, proxy: Option<Proxy>) -> Self {
+        self.proxy = proxy;
+        self
+    }
+
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// 替换合集接口的地址，用于测试
+    #[cfg(test)]
+    pub(crate) fn creative_url(mut self, creative_url: impl Into<String>) -> Self {
+        self.creative_url = creative_url.into();
+        self
+    }
+
+    pub fn build(self, login_info: LoginInfo) -> Result<BiliBili> {
+        let mut cookie_store = CookieStore::default();
+        insert_cookies(&mut cookie_store, &login_info.cookie_info)?;
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            REFERER,
+            HeaderValue::from_static("https://www.bilibili.com/"),
+        );
+        let client = proxied(reqwest::Client::builder(), self.proxy.as_ref())
+            .cookie_provider(Arc::new(CookieStoreMutex::new(cookie_store)))
+            .user_agent(self.user_agent)
+            .default_headers(headers)
+            .timeout(self.timeout)
+            .build()?;
+        Ok(BiliBili {
+            client_with_middleware: with_retry(client.clone(), &self.retry_policy),
+            client,
+            login_info,
+            creative_url: self.creative_url,
+        })
+    }
+}
+
+/// 沿用上传客户端的代理与重试策略
+impl From<&StatelessClient> for BiliBiliBuilder {
+    fn from(client: &StatelessClient) -> Self {
+        BiliBili::builder()
+            .proxy(client.proxy.clone())
+            .retry_policy(client.retry_policy.clone())
+    }
 }
 
 impl BiliBili {
+    pub fn builder() -> BiliBiliBuilder {
+        BiliBiliBuilder::default()
+    }
+
     pub async fn submit(&self, studio: &Studio) -> Result<ResponseData> {
-        let ret: ResponseData = reqwest::Client::builder()
-            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 Chrome/63.0.3239.108")
-            .timeout(Duration::new(60, 0))
-            .build()?
+        let ret: ResponseData = self
+            .client
             .post(format!(
                 "http://member.bilibili.com/x/vu/client/add?access_key={}",
                 self.login_info.token_info.access_token
@@ -254,7 +407,7 @@ impl BiliBili {
             info!("投稿成功");
             Ok(ret)
         } else {
-            Err(Kind::Custom(format!("{:?}", ret)))
+            Err(ret.to_error())
         }
     }
 
@@ -283,11 +436,10 @@ impl BiliBili {
             payload
         };
 
-        let ret: ResponseData = reqwest::Client::builder()
-            .user_agent("Mozilla/5.0 BiliDroid/7.80.0 (bbcallen@gmail.com) os/android model/MI 6 mobi_app/android build/7800300 channel/bili innerVer/7800310 osVer/13 network/2")
-            .timeout(Duration::new(60, 0))
-            .build()?
+        let ret: ResponseData = self
+            .client
             .post("https://member.bilibili.com/x/vu/app/add")
+            .header(USER_AGENT, "Mozilla/5.0 BiliDroid/7.80.0 (bbcallen@gmail.com) os/android model/MI 6 mobi_app/android build/7800300 channel/bili innerVer/7800310 osVer/13 network/2")
             .query(&payload)
             .json(studio)
             .send()
@@ -299,15 +451,48 @@ impl BiliBili {
             info!("APP接口投稿成功");
             Ok(ret)
         } else {
-            Err(Kind::Custom(format!("{:?}", ret)))
+            Err(ret.to_error())
+        }
+    }
+
+    /// 网页接口投稿，与网页端投稿一样需要 cookie 中的 bili_jct 作为 csrf
+    pub async fn submit_by_web(&self, studio: &Studio) -> Result<ResponseData> {
+        let csrf = self.csrf()?;
+        let mut payload = serde_json::to_value(studio)?;
+        payload["csrf"] = Value::from(csrf);
+        let ret: ResponseData = self
+            .client
+            .post("https://member.bilibili.com/x/vu/web/add/v3")
+            .query(&[
+                ("t", timestamp_millis().to_string().as_str()),
+                ("csrf", csrf),
+            ])
+            .json(&payload)
+            .send()
+            .await?
+            .json()
+            .await?;
+        info!("{:?}", ret);
+        if ret.code == 0 {
+            info!("网页接口投稿成功");
+            Ok(ret)
+        } else {
+            Err(ret.to_error())
+        }
+    }
+
+    /// 按 `option` 选择投稿接口
+    pub async fn submit_with(&self, studio: &Studio, option: SubmitOption) -> Result<ResponseData> {
+        match option {
+            SubmitOption::Client => self.submit(studio).await,
+            SubmitOption::App => self.submit_by_app(studio).await,
+            SubmitOption::Web => self.submit_by_web(studio).await,
         }
     }
 
     pub async fn edit(&self, studio: &Studio) -> Result<serde_json::Value> {
-        let ret: serde_json::Value = reqwest::Client::builder()
-            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 Chrome/63.0.3239.108")
-            .timeout(Duration::new(60, 0))
-            .build()?
+        let ret: serde_json::Value = self
+            .client
             .post(format!(
                 "http://member.bilibili.com/x/vu/client/edit?access_key={}",
                 self.login_info.token_info.access_token
@@ -322,16 +507,61 @@ impl BiliBili {
             info!("稿件修改成功");
             Ok(ret)
         } else {
-            Err(Kind::Custom(ret.to_string()))
+            Err(BiliApiError::from_value(&ret).into())
         }
     }
 
+    pub async fn edit_by_web(&self, studio: &Studio) -> Result<serde_json::Value> {
+        let csrf = self.csrf()?;
+        let mut payload = serde_json::to_value(studio)?;
+        payload["csrf"] = Value::from(csrf);
+        let ret: serde_json::Value = self
+            .client
+            .post("https://member.bilibili.com/x/vu/web/edit")
+            .query(&[
+                ("t", timestamp_millis().to_string().as_str()),
+                ("csrf", csrf),
+            ])
+            .json(&payload)
+            .send()
+            .await?
+            .json()
+            .await?;
+        info!("{}", ret);
+        if ret["code"] == 0 {
+            info!("网页接口稿件修改成功");
+            Ok(ret)
+        } else {
+            Err(BiliApiError::from_value(&ret).into())
+        }
+    }
+
+    /// 按 `option` 选择编辑接口，APP接口使用客户端接口编辑
+    pub async fn edit_with(&self, studio: &Studio, option: SubmitOption) -> Result<Value> {
+        match option {
+            SubmitOption::Web => self.edit_by_web(studio).await,
+            SubmitOption::Client | SubmitOption::App => self.edit(studio).await,
+        }
+    }
+
+    /// cookie 中的 bili_jct，网页接口用作 csrf
+    fn csrf(&self) -> Result<&str> {
+        Ok(self
+            .login_info
+            .cookie_info
+            .get("cookies")
+            .and_then(|c| c.as_array())
+            .ok_or("csrf cookie error")?
+            .iter()
+            .find(|c| c["name"] == "bili_jct")
+            .and_then(|c| c["value"].as_str())
+            .ok_or("csrf jct error")?)
+    }
+
     /// 查询视频的 json 信息
     pub async fn video_data(&self, vid: &Vid) -> Result<Value> {
-        let res: ResponseData = reqwest::Client::builder()
-            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 Chrome/63.0.3239.108")
-            .timeout(Duration::new(60, 0))
-            .build()?
+        let res: ResponseData = self
+            .client_with_middleware
             .get(format!(
                 "http://member.bilibili.com/x/client/archive/view?access_key={}&{vid}",
                 self.login_info.token_info.access_token
@@ -345,7 +575,7 @@ impl BiliBili {
                 code: _,
                 data: None,
                 ..
-            } => Err(Kind::Custom(format!("{res:?}"))),
+            } => Err(res.to_error()),
             ResponseData {
                 code: _,
                 data: Some(v),
@@ -354,6 +584,163 @@ impl BiliBili {
         }
     }
 
+    /// 查询稿件详情，包括各分P的转码与审核状态
+    pub async fn archive_view(&self, vid: &Vid) -> Result<ArchiveView> {
+        Ok(serde_json::from_value(self.video_data(vid).await?)?)
+    }
+
+    /// 为分P上传字幕并提交审核，`cid` 为分P的 cid
+    pub async fn upload_subtitle(
+        &self,
+        bvid: &str,
+        cid: u64,
+        lan: &str,
+        bcc: &Bcc,
+    ) -> Result<Value> {
+        let csrf = self.csrf()?;
+        let data = serde_json::to_string(bcc)?;
+        let ret: Value = self
+            .client
+            .post("https://api.bilibili.com/x/v2/dm/subtitle/draft/save")
+            .form(&[
+                ("type", "1"),
+                ("oid", cid.to_string().as_str()),
+                ("lan", lan),
+                ("data", data.as_str()),
+                ("submit", "true"),
+                ("sign", "false"),
+                ("bvid", bvid),
+                ("csrf", csrf),
+            ])
+            .send()
+            .await?
+            .json()
+            .await?;
+        info!("{}", ret);
+        if ret["code"] == 0 {
+            Ok(ret)
+        } else {
+            Err(BiliApiError::from_value(&ret).into())
+        }
+    }
+
+    /// 投稿后按服务器文件名找到分P的 cid，上传 `videos` 中附带的字幕
+    pub async fn upload_subtitles(&self, vid: &Vid, videos: &[Video]) -> Result<()> {
+        if videos.iter().all(|video| video.subtitles.is_empty()) {
+            return Ok(());
+        }
+        let view = self.archive_view(vid).await?;
+        for video in videos {
+            if video.subtitles.is_empty() {
+                continue;
+            }
+            let cid = view
+                .videos
+                .iter()
+                .find(|part| part.filename == video.filename)
+                .map(|part| part.cid)
+                .ok_or_else(|| Kind::Custom(format!("{} not found in {vid}", video.filename)))?;
+            for subtitle in &video.subtitles {
+                let bcc = Bcc::load(&subtitle.path)?;
+                self.upload_subtitle(&view.archive.bvid, cid, &subtitle.lan, &bcc)
+                    .await?;
+                info!("字幕上传成功: {}", subtitle.path.display());
+            }
+        }
+        Ok(())
+    }
+
+    /// 删除稿件，不可恢复；删除单个分P可通过 [`StudioEdit`](crate::uploader::edit::StudioEdit) 修改稿件
+    pub async fn delete_archive(&self, vid: &Vid) -> Result<Value> {
+        let aid = match vid {
+            Vid::Aid(aid) => *aid,
+            Vid::Bvid(_) => self.archive_view(vid).await?.archive.aid,
+        };
+        let csrf = self.csrf()?;
+        let ret: Value = self
+            .client
+            .post("https://member.bilibili.com/x/web/archive/delete")
+            .form(&[("aid", aid.to_string().as_str()), ("csrf", csrf)])
+            .send()
+            .await?
+            .json()
+            .await?;
+        info!("{}", ret);
+        if ret["code"] == 0 {
+            info!("稿件 av{aid} 已删除");
+            Ok(ret)
+        } else {
+            Err(BiliApiError::from_value(&ret).into())
+        }
+    }
+
+    /// 轮询稿件直到过审、等待定时发布或被退回，状态变化时调用 `on_change`；
+    /// 超时后返回最后一次查询到的状态。
+    /// 服务器繁忙、稿件暂时查询不到等错误按轮询间隔退避重试，连续失败 [`WATCH_MAX_ERRORS`] 次后返回错误
+    pub async fn watch(
+        &self,
+        vid: &Vid,
+        options: &WatchOptions,
+        mut on_change: impl FnMut(&ReviewState, &ArchiveView),
+    ) -> Result<ReviewState> {
+        let started = tokio::time::Instant::now();
+        let deadline = options.timeout.map(|timeout| started + timeout);
+        let mut interval = options.interval;
+        let mut last = None;
+        // 宽限期内被忽略的最终状态与最近一次查询错误，超时时没有其他结果才返回
+        let mut stale = None;
+        let mut error = None;
+        let mut errors = 0;
+        // 见到中间状态后，之后的最终状态才是本次投稿或编辑的结果
+        let mut pending = false;
+        loop {
+            match self.archive_view(vid).await {
+                Ok(view) => {
+                    errors = 0;
+                    let state = view.review_state();
+                    if state.is_final() && !pending && started.elapsed() < options.grace {
+                        interval = options.interval;
+                        stale = Some(state);
+                    } else {
+                        if last.as_ref() == Some(&state) {
+                            interval = (interval * 2).min(options.max_interval);
+                        } else {
+                            on_change(&state, &view);
+                            interval = options.interval;
+                        }
+                        if state.is_final() {
+                            return Ok(state);
+                        }
+                        pending = true;
+                        last = Some(state);
+                    }
+                }
+                Err(e) if is_transient(&e) && errors < WATCH_MAX_ERRORS => {
+                    errors += 1;
+                    interval = (options.interval * 2u32.pow(errors - 1)).min(options.max_interval);
+                    warn!("failed to query {vid} ({errors}/{WATCH_MAX_ERRORS}): {e}");
+                    error = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+            let wait = match deadline {
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(tokio::time::Instant::now());
+                    if left.is_zero() {
+                        return match (last.or(stale), error) {
+                            (Some(state), _) => Ok(state),
+                            (None, Some(e)) => Err(e),
+                            (None, None) => Err(Kind::Custom(format!("{vid} 查询超时"))),
+                        };
+                    }
+                    interval.min(left)
+                }
+                None => interval,
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
     pub async fn studio_data(&self, vid: &Vid) -> Result<Studio> {
         let mut video_info = self.video_data(vid).await?;
 
@@ -366,7 +753,7 @@ impl BiliBili {
 
     pub async fn my_info(&self) -> Result<Value> {
         Ok(self
-            .client
+            .client_with_middleware
             .get("https://api.bilibili.com/x/space/myinfo")
             .send()
             .await?
@@ -376,7 +763,7 @@ impl BiliBili {
 
     pub async fn archive_pre(&self) -> Result<Value> {
         Ok(self
-            .client
+            .client_with_middleware
             .get("https://member.bilibili.com/x/vupre/web/archive/pre")
             .send()
             .await?
@@ -384,9 +771,28 @@ impl BiliBili {
             .await?)
     }
 
+    /// 解析后的分区列表
+    pub async fn archive_pre_data(&self) -> Result<ArchivePre> {
+        let result: ResponseData<ArchivePre> = self
+            .client_with_middleware
+            .get("https://member.bilibili.com/x/vupre/web/archive/pre")
+            .send()
+            .await?
+            .json()
+            .await?;
+        match result {
+            ResponseData {
+                code: 0,
+                data: Some(data),
+                ..
+            } => Ok(data),
+            _ => Err(result.to_error()),
+        }
+    }
+
     pub async fn recommend_tag(&self, subtype_id: u16, title: &str, key: &str) -> Result<Value> {
         let result: ResponseData = self
-            .client
+            .client_with_middleware
             .get(format!("https://member.bilibili.com/x/vupre/web/tag/recommend?upload_id=&subtype_id={subtype_id}&title={title}&filename={key}&description=&cover_url=&t="))
             .send()
             .await?
@@ -395,26 +801,17 @@ impl BiliBili {
         if result.code == 0 {
             return Ok(result.data.unwrap_or_default());
         }
-        Err(Kind::Custom(result.message))
+        Err(result.to_error())
     }
 
     pub async fn cover_up(&self, input: &[u8]) -> Result<String> {
-        let csrf = self
-            .login_info
-            .cookie_info
-            .get("cookies")
-            .and_then(|c| c.as_array())
-            .ok_or("cover_up cookie error")?
-            .iter()
-            .filter_map(|c| c.as_object())
-            .find(|c| c["name"] == "bili_jct")
-            .ok_or("cover_up jct error")?;
+        let csrf = self.csrf()?;
         let response = self
             .client
             .post("https://member.bilibili.com/x/vu/web/cover/up")
             .form(&json!({
                 "cover": format!("data:image/jpeg;base64,{}", base64::Engine::encode(&base64::engine::general_purpose::STANDARD, input)),
-                "csrf": csrf["value"]
+                "csrf": csrf
             }))
             .send()
             .await?;
@@ -432,7 +829,7 @@ impl BiliBili {
         {
             Ok(value["url"].as_str().ok_or("cover_up error")?.into())
         } else {
-            Err(Kind::Custom(format!("{res:?}")))
+            Err(res.to_error())
         }
     }
 
@@ -442,29 +839,8 @@ impl BiliBili {
         let params = [("status", status), ("pn", &page_num.to_string())];
         let url = reqwest::Url::parse_with_params(url_str, &params).unwrap();
 
-        let cookie = self
-            .login_info
-            .cookie_info
-            .get("cookies")
-            .and_then(|c: &Value| c.as_array())
-            .ok_or("archives cookie error")?
-            .iter()
-            .filter_map(|c| match (c["name"].as_str(), c["value"].as_str()) {
-                (Some(name), Some(value)) => Some((name, value)),
-                _ => None,
-            })
-            .map(|c| format!("{}={}", c.0, c.1))
-            .collect::<Vec<_>>()
-            .join("; ");
-
-        let jar = reqwest::cookie::Jar::default();
-        jar.add_cookie_str(&cookie, &url);
-
-        let res: ResponseData = reqwest::Client::builder()
-            .user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 Chrome/63.0.3239.108")
-            .cookie_provider(std::sync::Arc::new(jar))
-            .timeout(Duration::new(60, 0))
-            .build()?
+        let res: ResponseData = self
+            .client_with_middleware
             .get(url)
             .send()
             .await?
@@ -476,7 +852,7 @@ impl BiliBili {
                 code: _,
                 data: None,
                 ..
-            } => Err(Kind::Custom(format!("{:?}", res))),
+            } => Err(res.to_error()),
             ResponseData {
                 code: _,
                 data: Some(v),
@@ -530,6 +906,175 @@ impl BiliBili {
 
         Ok(studios)
     }
+
+    /// 合集列表，包含各合集的小节
+    pub async fn seasons(&self) -> Result<Vec<Season>> {
+        let mut seasons = Vec::new();
+        for pn in 1.. {
+            let res: ResponseData = self
+                .client_with_middleware
+                .get(format!("{}/seasons", self.creative_url))
+                .query(&[
+                    ("pn", pn.to_string().as_str()),
+                    ("ps", "30"),
+                    ("order", "desc"),
+                    ("sort", "mtime"),
+                ])
+                .send()
+                .await?
+                .json()
+                .await?;
+            let mut data = res.into_data()?;
+            let page: Option<Vec<Season>> = serde_json::from_value(data["seasons"].take())?;
+            let page = page.unwrap_or_default();
+            let total = data["total"].as_u64().unwrap_or_default();
+            let last = page.is_empty();
+            seasons.extend(page);
+            if last || seasons.len() as u64 >= total {
+                break;
+            }
+        }
+        Ok(seasons)
+    }
+
+    /// 小节中的单集
+    pub async fn section_episodes(&self, section_id: u64) -> Result<Vec<Episode>> {
+        let res: ResponseData = self
+            .client_with_middleware
+            .get(format!("{}/season/section", self.creative_url))
+            .query(&[("id", section_id)])
+            .send()
+            .await?
+            .json()
+            .await?;
+        let episodes: Option<Vec<Episode>> =
+            serde_json::from_value(res.into_data()?["episodes"].take())?;
+        Ok(episodes.unwrap_or_default())
+    }
+
+    /// 创建合集，返回合集的 id，`cover` 需先通过 [`BiliBili::cover_up`] 上传
+    pub async fn create_season(&self, title: &str, desc: &str, cover: &str) -> Result<u32> {
+        let csrf = self.csrf()?;
+        let res: ResponseData = self
+            .client
+            .post(format!("{}/season/add", self.creative_url))
+            .query(&[("csrf", csrf)])
+            .json(&json!({
+                "title": title,
+                "desc": desc,
+                "cover": cover,
+                "season_price": 0,
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        info!("{}", res);
+        res.into_data()?
+            .as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or_else(|| Kind::Custom("create season: missing season id".into()))
+    }
+
+    /// 把稿件加入合集的小节，单集标题为稿件标题
+    pub async fn add_to_section(&self, section_id: u64, vid: &Vid) -> Result<()> {
+        let view = self.archive_view(vid).await?;
+        let cid = view
+            .videos
+            .first()
+            .map(|video| video.cid)
+            .ok_or_else(|| Kind::Custom(format!("{vid} has no parts")))?;
+        let csrf = self.csrf()?;
+        let res: ResponseData = self
+            .client
+            .post(format!("{}/season/section/episodes/add", self.creative_url))
+            .query(&[("csrf", csrf)])
+            .json(&json!({
+                "sectionId": section_id,
+                "episodes": [{
+                    "title": view.archive.title,
+                    "aid": view.archive.aid,
+                    "cid": cid,
+                    "charging_pay": 0,
+                }],
+                "csrf": csrf,
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        info!("{}", res);
+        res.into_data()?;
+        Ok(())
+    }
+
+    /// 把稿件加入合集的第一个小节，未开启小节的合集只有这一个小节
+    pub async fn add_to_season(&self, season_id: u32, vid: &Vid) -> Result<()> {
+        let section = self
+            .seasons()
+            .await?
+            .into_iter()
+            .find(|season| season.id == season_id)
+            .ok_or_else(|| Kind::Custom(format!("season {season_id} not found")))?
+            .sections
+            .into_iter()
+            .next()
+            .ok_or_else(|| Kind::Custom(format!("season {season_id} has no sections")))?;
+        self.add_to_section(section.id, vid).await
+    }
+
+    /// 从合集中移除单集，`episode_id` 为 [`Episode::id`]
+    pub async fn remove_episode(&self, episode_id: u64) -> Result<()> {
+        let csrf = self.csrf()?;
+        let res: ResponseData = self
+            .client
+            .post(format!("{}/season/section/episode/del", self.creative_url))
+            .form(&[("id", episode_id.to_string().as_str()), ("csrf", csrf)])
+            .send()
+            .await?
+            .json()
+            .await?;
+        info!("{}", res);
+        res.into_data()?;
+        Ok(())
+    }
+
+    /// 按 `episode_ids` 的顺序重新排列小节中的单集
+    pub async fn sort_section(&self, section: &Section, episode_ids: &[u64]) -> Result<()> {
+        let csrf = self.csrf()?;
+        let sorts: Vec<_> = episode_ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| json!({"id": id, "sort": i + 1}))
+            .collect();
+        let res: ResponseData = self
+            .client
+            .post(format!("{}/season/section/edit", self.creative_url))
+            .query(&[("csrf", csrf)])
+            .json(&json!({
+                "section": {
+                    "id": section.id,
+                    "type": section.section_type,
+                    "seasonId": section.season_id,
+                    "title": section.title,
+                },
+                "sorts": sorts,
+            }))
+            .send()
+            .await?
+            .json()
+            .await?;
+        info!("{}", res);
+        res.into_data()?;
+        Ok(())
+    }
+}
+
+fn timestamp_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -540,6 +1085,37 @@ pub struct ResponseData<T = Value> {
     ttl: Option<u8>,
 }
 
+impl ResponseData {
+    /// 返回码不为 0 时返回错误
+    fn into_data(self) -> Result<Value> {
+        if self.code == 0 {
+            Ok(self.data.unwrap_or_default())
+        } else {
+            Err(self.to_error())
+        }
+    }
+
+    /// 投稿接口返回的稿件 bv 号
+    pub fn vid(&self) -> Option<Vid> {
+        self.data
+            .as_ref()
+            .and_then(|data| data.get("bvid"))
+            .and_then(|vid| vid.as_str())
+            .map(|vid| Vid::Bvid(vid.into()))
+    }
+}
+
+impl<T: Serialize> ResponseData<T> {
+    /// 返回码不为 0 时为 [`BiliApiError`]，否则为返回内容不符合预期
+    pub fn to_error(&self) -> Kind {
+        if self.code != 0 {
+            BiliApiError::new(self.code, &self.message).into()
+        } else {
+            Kind::Custom(self.to_string())
+        }
+    }
+}
+
 impl<T: Serialize> Display for ResponseData<T> {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -549,3 +1125,215 @@ impl<T: Serialize> Display for ResponseData<T> {
         )
     }
 }
+
+/// [`BiliBili::watch`] 连续查询失败的最大次数
+pub const WATCH_MAX_ERRORS: u32 = 5;
+
+/// 稍后重试可能成功的查询错误，刚提交的稿件可能暂时查询不到
+fn is_transient(e: &Kind) -> bool {
+    match e {
+        Kind::BiliApi(e) => e.is_retryable() || e.kind() == ApiErrorCode::NotFound,
+        Kind::Reqwest(_) | Kind::ReqwestMiddleware(_) => {
+            !matches!(e.class(), ErrorClass::ClientError | ErrorClass::Fatal)
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{body_partial_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// 接口地址为 http 时可以由 `proxy` 代为响应
+    fn proxied_bili(proxy: &MockServer) -> Result<BiliBili> {
+        let login_info: LoginInfo = serde_json::from_value(json!({
+            "cookie_info": {"cookies": [{"name": "SESSDATA", "value": "abc"}]},
+            "sso": [],
+            "token_info": {"access_token": "token", "expires_in": 0, "mid": 0, "refresh_token": ""},
+            "platform": null,
+        }))?;
+        BiliBili::builder()
+            .user_agent("biliup-test")
+            .proxy(Some(Proxy::http(proxy.uri())?))
+            .build(login_info)
+    }
+
+    #[tokio::test]
+    async fn builder_client_carries_cookies_and_user_agent() -> Result<()> {
+        let proxy = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/x/client/archive/view"))
+            .and(header("cookie", "SESSDATA=abc"))
+            .and(header("user-agent", "biliup-test"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"code": 0, "message": "0", "data": {"archive": {}}})),
+            )
+            .expect(1)
+            .mount(&proxy)
+            .await;
+        let bili = proxied_bili(&proxy)?;
+        let data = bili.video_data(&Vid::Aid(1)).await?;
+        assert_eq!(data, json!({"archive": {}}));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn watch_reports_transitions_until_final() -> Result<()> {
+        let proxy = MockServer::start().await;
+        let view = |state: i16| {
+            ResponseTemplate::new(200).set_body_json(
+                json!({"code": 0, "message": "0", "data": {"archive": {"state": state}}}),
+            )
+        };
+        Mock::given(path("/x/client/archive/view"))
+            .respond_with(view(-30))
+            .up_to_n_times(2)
+            .with_priority(1)
+            .mount(&proxy)
+            .await;
+        Mock::given(path("/x/client/archive/view"))
+            .respond_with(view(0))
+            .mount(&proxy)
+            .await;
+        let options = WatchOptions {
+            interval: Duration::from_millis(1),
+            ..Default::default()
+        };
+        let mut states = Vec::new();
+        let state = proxied_bili(&proxy)?
+            .watch(&Vid::Aid(1), &options, |state, _| {
+                states.push(state.clone())
+            })
+            .await?;
+        assert_eq!(state, ReviewState::Published);
+        assert_eq!(states, [ReviewState::Transcoding, ReviewState::Published]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn watch_skips_stale_state_and_transient_errors() -> Result<()> {
+        let proxy = MockServer::start().await;
+        let view = |state: i16| {
+            ResponseTemplate::new(200).set_body_json(
+                json!({"code": 0, "message": "0", "data": {"archive": {"state": state}}}),
+            )
+        };
+        let error = |code: i32| {
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"code": code, "message": "error", "data": null}))
+        };
+        // 编辑前的状态、稿件暂时不可见、服务繁忙，之后才开始转码
+        for (priority, response) in [view(0), error(-404), error(-503), view(-30)]
+            .into_iter()
+            .enumerate()
+        {
+            Mock::given(path("/x/client/archive/view"))
+                .respond_with(response)
+                .up_to_n_times(1)
+                .with_priority(priority as u8 + 1)
+                .mount(&proxy)
+                .await;
+        }
+        Mock::given(path("/x/client/archive/view"))
+            .respond_with(view(0))
+            .mount(&proxy)
+            .await;
+        let options = WatchOptions {
+            interval: Duration::from_millis(1),
+            grace: Duration::from_secs(60),
+            ..Default::default()
+        };
+        let mut states = Vec::new();
+        let state = proxied_bili(&proxy)?
+            .watch(&Vid::Aid(1), &options, |state, _| {
+                states.push(state.clone())
+            })
+            .await?;
+        assert_eq!(state, ReviewState::Published);
+        assert_eq!(states, [ReviewState::Transcoding, ReviewState::Published]);
+
+        // 非暂时性的错误直接返回
+        proxy.reset().await;
+        Mock::given(path("/x/client/archive/view"))
+            .respond_with(error(-403))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+        assert!(proxied_bili(&proxy)?
+            .watch(&Vid::Aid(1), &options, |_, _| {})
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn creates_season_and_adds_archive() -> Result<()> {
+        let server = MockServer::start().await;
+        let login_info: LoginInfo = serde_json::from_value(json!({
+            "cookie_info": {"cookies": [{"name": "bili_jct", "value": "jct"}]},
+            "sso": [],
+            "token_info": {"access_token": "token", "expires_in": 0, "mid": 0, "refresh_token": ""},
+            "platform": null,
+        }))?;
+        let bili = BiliBili::builder()
+            .proxy(Some(Proxy::http(server.uri())?))
+            .creative_url(server.uri())
+            .build(login_info)?;
+        let ok = |data: Value| {
+            ResponseTemplate::new(200)
+                .set_body_json(json!({"code": 0, "message": "0", "data": data}))
+        };
+        Mock::given(method("POST"))
+            .and(path("/season/add"))
+            .and(query_param("csrf", "jct"))
+            .and(body_partial_json(json!({"title": "合集", "desc": "简介"})))
+            .respond_with(ok(json!(10)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        assert_eq!(bili.create_season("合集", "简介", "").await?, 10);
+
+        Mock::given(method("GET"))
+            .and(path("/seasons"))
+            .respond_with(ok(json!({
+                "seasons": [{
+                    "season": {"id": 10, "title": "合集"},
+                    "sections": {"sections": [{"id": 20, "seasonId": 10, "title": "正片"}]}
+                }],
+                "total": 1
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(path("/x/client/archive/view"))
+            .respond_with(ok(json!({
+                "archive": {"aid": 1, "title": "录播"},
+                "videos": [{"cid": 2}]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/season/section/episodes/add"))
+            .and(query_param("csrf", "jct"))
+            .and(body_partial_json(json!({
+                "sectionId": 20,
+                "episodes": [{"title": "录播", "aid": 1, "cid": 2, "charging_pay": 0}]
+            })))
+            .respond_with(ok(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        bili.add_to_season(10, &Vid::Aid(1)).await?;
+        assert!(bili.add_to_season(11, &Vid::Aid(1)).await.is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn submit_option_from_config() {
+        let option: SubmitOption = serde_yaml::from_str("web").unwrap();
+        assert_eq!(option, SubmitOption::Web);
+        assert_eq!(SubmitOption::default(), SubmitOption::Client);
+    }
+}
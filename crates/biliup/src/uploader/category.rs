@@ -0,0 +1,179 @@
+use crate::error::{Kind, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// 投稿页的分区与活动，对应 `x/vupre/web/archive/pre` 返回的 `data`
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ArchivePre {
+    /// 一级分区，投稿时使用其下的子分区
+    pub typelist: Vec<Category>,
+    /// 可参与的活动与话题
+    pub activities: Vec<Activity>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Category {
+    pub id: u16,
+    /// 上级分区，一级分区为 0
+    pub parent: u16,
+    pub name: String,
+    pub desc: String,
+    pub children: Vec<Category>,
+}
+
+/// 活动，投稿时将 `id` 填入 `mission_id` 即可参与
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Activity {
+    pub id: u64,
+    pub name: String,
+    pub topic_id: u64,
+    pub protocol: String,
+    /// 开始与结束时间，10位时间戳
+    pub stime: u64,
+    pub etime: u64,
+}
+
+impl ArchivePre {
+    /// 所有可投稿的子分区
+    pub fn categories(&self) -> impl Iterator<Item = &Category> {
+        self.typelist.iter().flat_map(|parent| &parent.children)
+    }
+
+    /// 按 id 查找子分区
+    pub fn find(&self, tid: u16) -> Option<&Category> {
+        self.categories().find(|c| c.id == tid)
+    }
+
+    /// 按名称查找子分区，名称重复时报错
+    pub fn find_by_name(&self, name: &str) -> Result<&Category> {
+        let found: Vec<_> = self.categories().filter(|c| c.name == name).collect();
+        match found[..] {
+            [category] => Ok(category),
+            [] => Err(Kind::Custom(format!("分区 {name} 不存在"))),
+            _ => Err(Kind::Custom(format!(
+                "分区名称 {name} 不唯一，请使用 id: {}",
+                found
+                    .iter()
+                    .map(|c| format!("{} ({})", c.id, self.parent_name(c)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ))),
+        }
+    }
+
+    /// 名称或简介中含有 `keyword` 的子分区，一级分区匹配时包含其下所有子分区
+    pub fn search<'a>(&'a self, keyword: &'a str) -> impl Iterator<Item = &'a Category> {
+        self.typelist.iter().flat_map(move |parent| {
+            let all = parent.name.contains(keyword);
+            parent
+                .children
+                .iter()
+                .filter(move |c| all || c.name.contains(keyword) || c.desc.contains(keyword))
+        })
+    }
+
+    pub fn parent_name(&self, category: &Category) -> &str {
+        self.typelist
+            .iter()
+            .find(|parent| parent.id == category.parent)
+            .map(|parent| parent.name.as_str())
+            .unwrap_or_default()
+    }
+}
+
+/// 命令行或配置文件中填写的分区，可以是 id 或子分区名称，
+/// 通过 [`Tid::resolve`] 转换为 [`Studio::tid`](crate::uploader::bilibili::Studio::tid)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tid {
+    Id(u16),
+    Name(String),
+}
+
+impl Tid {
+    /// 转换为分区 id，名称需要 `archive_pre` 中的分区列表
+    pub fn resolve(&self, archive_pre: Option<&ArchivePre>) -> Result<u16> {
+        match (self, archive_pre) {
+            (Tid::Id(id), _) => Ok(*id),
+            (Tid::Name(name), Some(archive_pre)) => Ok(archive_pre.find_by_name(name)?.id),
+            (Tid::Name(name), None) => Err(Kind::Custom(format!(
+                "无法获取分区列表，请使用分区 id 代替 {name}"
+            ))),
+        }
+    }
+}
+
+impl From<u16> for Tid {
+    fn from(id: u16) -> Self {
+        Tid::Id(id)
+    }
+}
+
+impl FromStr for Tid {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err("tid is empty".into());
+        }
+        Ok(s.parse()
+            .map(Tid::Id)
+            .unwrap_or_else(|_| Tid::Name(s.into())))
+    }
+}
+
+impl Display for Tid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Tid::Id(id) => write!(f, "{id}"),
+            Tid::Name(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn finds_categories_by_id_or_name() -> Result<()> {
+        let pre: ArchivePre = serde_json::from_value(json!({
+            "typelist": [
+                {"id": 4, "parent": 0, "name": "游戏", "children": [
+                    {"id": 17, "parent": 4, "name": "单机游戏", "desc": "以单机游戏为主要内容"},
+                    {"id": 171, "parent": 4, "name": "电子竞技", "desc": "", "max_video_count": 100}
+                ]},
+                {"id": 160, "parent": 0, "name": "生活", "children": [
+                    {"id": 21, "parent": 160, "name": "日常", "desc": ""}
+                ]},
+                {"id": 217, "parent": 0, "name": "动物圈", "children": [
+                    {"id": 222, "parent": 217, "name": "日常", "desc": ""}
+                ]}
+            ],
+            "activities": [{"id": 1, "name": "活动", "topic_id": 2, "hot": 1}]
+        }))?;
+        assert_eq!(pre.find(171).map(|c| c.name.as_str()), Some("电子竞技"));
+        assert!(pre.find(4).is_none());
+        assert!(pre
+            .find_by_name("日常")
+            .unwrap_err()
+            .to_string()
+            .contains("21 (生活), 222 (动物圈)"));
+        let found: Vec<_> = pre.search("游戏").map(|c| c.id).collect();
+        assert_eq!(found, [17, 171]);
+        assert_eq!(pre.activities[0].topic_id, 2);
+
+        let tid: Tid = "单机游戏".parse().map_err(Kind::Custom)?;
+        assert_eq!(tid, Tid::Name("单机游戏".into()));
+        assert_eq!(tid.resolve(Some(&pre))?, 17);
+        assert!(tid.resolve(None).is_err());
+        let tid: Tid = " 171 ".parse().map_err(Kind::Custom)?;
+        assert_eq!(tid.resolve(None)?, 171);
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use futures::{Stream, StreamExt};
+use reqwest::Body;
+use std::sync::Arc;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 限制所有文件同时上传中的分块总数，克隆后共享同一份额度
+#[derive(Clone, Debug)]
+pub struct ChunkLimiter {
+    max_chunks: usize,
+    semaphore: Arc<Semaphore>,
+}
+
+/// 持有额度的分块，分块上传结束被丢弃后归还额度
+#[derive(Clone)]
+pub struct Permitted<B> {
+    chunk: B,
+    _permit: Arc<OwnedSemaphorePermit>,
+}
+
+impl ChunkLimiter {
+    /// `max_chunks` 为 0 时按 1 处理
+    pub fn new(max_chunks: usize) -> Self {
+        let max_chunks = max_chunks.max(1);
+        Self {
+            max_chunks,
+            semaphore: Arc::new(Semaphore::new(max_chunks)),
+        }
+    }
+
+    pub fn max_chunks(&self) -> usize {
+        self.max_chunks
+    }
+
+    /// 为每个分块取得额度后再交给上传线路，额度随分块一起释放
+    pub fn limit<S, B, E>(&self, stream: S) -> impl Stream<Item = Result<(Permitted<B>, usize), E>>
+    where
+        S: Stream<Item = Result<(B, usize), E>>,
+    {
+        let semaphore = self.semaphore.clone();
+        stream.then(move |chunk| {
+            let semaphore = semaphore.clone();
+            async move {
+                let (chunk, len) = chunk?;
+                let permit = semaphore
+                    .acquire_owned()
+                    .await
+                    .expect("semaphore is never closed");
+                Ok((
+                    Permitted {
+                        chunk,
+                        _permit: Arc::new(permit),
+                    },
+                    len,
+                ))
+            }
+        })
+    }
+}
+
+impl<B: Into<Body>> From<Permitted<B>> for Body {
+    fn from(permitted: Permitted<B>) -> Self {
+        permitted.chunk.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn limit_holds_permits_until_chunks_dropped() {
+        let limiter = ChunkLimiter::new(2);
+        let chunks = stream::iter((0..3).map(|i| Ok::<_, ()>((i, 1))));
+        let stream = limiter.limit(chunks);
+        tokio::pin!(stream);
+        let first = stream.next().await.unwrap().unwrap();
+        let _second = stream.next().await.unwrap().unwrap();
+        // 额度用尽，第三个分块需要等待
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), stream.next())
+                .await
+                .is_err()
+        );
+        drop(first);
+        let third = stream.next().await.unwrap().unwrap();
+        assert_eq!(third.0.chunk, 2);
+    }
+
+    #[tokio::test]
+    async fn zero_max_chunks_allows_one_chunk() {
+        let limiter = ChunkLimiter::new(0);
+        assert_eq!(limiter.max_chunks(), 1);
+        let chunks = stream::iter([Ok::<_, ()>((0, 1))]);
+        let stream = limiter.limit(chunks);
+        tokio::pin!(stream);
+        assert!(stream.next().await.unwrap().is_ok());
+    }
+}
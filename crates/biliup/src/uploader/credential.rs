@@ -1,15 +1,16 @@
 use crate::client::StatefulClient;
 use futures::Future;
-use reqwest::header;
+use reqwest::{header, Proxy};
 use std::io::Seek;
 use std::path::Path;
 
 use crate::error::{Kind, Result};
-use crate::uploader::bilibili::{BiliBili, ResponseData};
+use crate::uploader::bilibili::{BiliBili, BiliBiliBuilder, ResponseData};
 use base64::{engine::general_purpose, Engine as _};
 use cookie::Cookie;
 use md5::{Digest, Md5};
 use reqwest::header::{COOKIE, ORIGIN, REFERER, USER_AGENT};
+use reqwest_cookie_store::CookieStore;
 
 use rsa::{pkcs8::DecodePublicKey, Pkcs1v15Encrypt, RsaPublicKey};
 use serde::{Deserialize, Serialize};
@@ -52,7 +53,23 @@ impl AppKeyStore {
 }
 
 pub async fn login_by_cookies(file: impl AsRef<Path>) -> Result<BiliBili> {
-    let client = Credential::new();
+    login_by_cookies_with_proxy(file, None).await
+}
+
+/// 登录及之后的投稿、查询稿件等请求都经过 `proxy`
+pub async fn login_by_cookies_with_proxy(
+    file: impl AsRef<Path>,
+    proxy: Option<Proxy>,
+) -> Result<BiliBili> {
+    login_by_cookies_with(file, BiliBili::builder().proxy(proxy)).await
+}
+
+/// 验证并在需要时刷新登录信息，再由 `builder` 创建 [`BiliBili`]
+pub async fn login_by_cookies_with(
+    file: impl AsRef<Path>,
+    builder: BiliBiliBuilder,
+) -> Result<BiliBili> {
+    let client = Credential::with_proxy(builder.proxy.as_ref());
     // let path = file.as_ref();
     let mut file = std::fs::File::options().read(true).write(true).open(file)?;
     let login_info: LoginInfo = serde_json::from_reader(std::io::BufReader::new(&file))?;
@@ -80,12 +97,9 @@ pub async fn login_by_cookies(file: impl AsRef<Path>) -> Result<BiliBili> {
             info!("无需更新cookie");
             login_info
         }
-        _ => return Err(Kind::Custom(response.to_string())),
+        _ => return Err(response.to_error()),
     };
-    Ok(BiliBili {
-        client: client.0.client,
-        login_info,
-    })
+    builder.build(login_info)
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
@@ -111,7 +125,7 @@ pub struct LoginInfo {
 pub struct TokenInfo {
     pub access_token: String,
     expires_in: u32,
-    mid: u64,
+    pub mid: u64,
     refresh_token: String,
 }
 
@@ -128,12 +142,16 @@ pub struct Credential(StatefulClient);
 
 impl Credential {
     pub fn new() -> Self {
+        Self::with_proxy(None)
+    }
+
+    pub fn with_proxy(proxy: Option<&Proxy>) -> Self {
         let mut headers = header::HeaderMap::new();
         headers.insert(
             "Referer",
             header::HeaderValue::from_static("https://www.bilibili.com/"),
         );
-        Self(StatefulClient::new(headers))
+        Self(StatefulClient::new(headers, proxy))
     }
 
     async fn validate_tokens(&self, login_info: &LoginInfo) -> Result<ResponseData<ResponseValue>> {
@@ -203,7 +221,7 @@ impl Credential {
                     ..info
                 })
             }
-            _ => Err(Kind::Custom(response.to_string())),
+            _ => Err(response.to_error()),
         }
     }
 
@@ -255,7 +273,7 @@ impl Credential {
                     ..info
                 })
             }
-            _ => Err(Kind::Custom(response.to_string())),
+            _ => Err(response.to_error()),
         }
     }
 
@@ -282,7 +300,7 @@ impl Credential {
                 platform: Some("Android".to_string()),
                 ..info
             }),
-            _ => Err(Kind::Custom(res.to_string())),
+            _ => Err(res.to_error()),
         }
     }
 
@@ -295,7 +313,7 @@ impl Credential {
             .await
     }
 
-    pub async fn send_sms_handle_recaptcha<'a, F, Fut>(
+    pub async fn send_sms_handle_recaptcha<F, Fut>(
         &self,
         phone_number: u64,
         country_code: u32,
@@ -398,7 +416,7 @@ impl Credential {
                 let url = data["recaptcha_url"].as_str().unwrap().to_string();
                 Err(Kind::NeedRecaptcha(url))
             }
-            _ => Err(Kind::Custom(res.to_string())),
+            _ => Err(res.to_error()),
         }
     }
 
@@ -441,7 +459,7 @@ impl Credential {
                     // form["ts"] = Value::from(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs());
                 }
                 _ => {
-                    break Err(Kind::Custom(format!("{res:#?}")));
+                    break Err(res.to_error());
                 }
             }
         }
@@ -472,7 +490,7 @@ impl Credential {
             "appkey": AppKeyStore::Android.app_key(),
             "sign": Credential::sign(&format!("appkey={}", AppKeyStore::Android.app_key()), AppKeyStore::Android.appsec()),
         });
-        let response: Value = self
+        let res: ResponseData = self
             .0
             .client
             .get("https://passport.bilibili.com/x/passport-login/web/key")
@@ -481,18 +499,15 @@ impl Credential {
             .await?
             .json()
             .await?;
-        let response = response
-            .get("data")
-            .ok_or_else(|| Kind::Custom(response.to_string()))?;
-        let hash = response
-            .get("hash")
-            .and_then(Value::as_str)
-            .ok_or_else(|| Kind::Custom(response.to_string()))?;
-        let key = response
-            .get("key")
-            .and_then(Value::as_str)
-            .ok_or_else(|| Kind::Custom(response.to_string()))?;
-        Ok((hash.to_string(), key.to_string()))
+        let field = |name| {
+            res.data
+                .as_ref()
+                .filter(|_| res.code == 0)
+                .and_then(|data| data.get(name))
+                .and_then(Value::as_str)
+                .ok_or_else(|| res.to_error())
+        };
+        Ok((field("hash")?.to_string(), field("key")?.to_string()))
     }
 
     pub async fn login_by_web_qrcode(
@@ -561,12 +576,13 @@ impl Credential {
             .form(&form)
             .send()
             .await?;
-        if !response.status().is_success() {
-            return Err(Kind::Custom(response.text().await?));
-        }
-        let res: ResponseData = response.json().await?;
-        if res.code != 0 {
-            return Err(Kind::Custom(format!("{res:#?}")));
+        // 请求失败时返回内容通常仍是带错误码的 json
+        let status = response.status();
+        let text = response.text().await?;
+        let res: ResponseData =
+            serde_json::from_str(&text).map_err(|_| Kind::Custom(format!("{status}: {text}")))?;
+        if !status.is_success() || res.code != 0 {
+            return Err(res.to_error());
         }
         Ok(())
     }
@@ -582,18 +598,7 @@ impl Credential {
 
     fn set_cookie(&self, cookie_info: &serde_json::Value) {
         let mut store = self.0.cookie_store.lock().unwrap();
-        for cookie in cookie_info["cookies"].as_array().unwrap() {
-            let cookie = Cookie::build(
-                cookie["name"].as_str().unwrap(),
-                cookie["value"].as_str().unwrap(),
-            )
-            .domain("bilibili.com")
-            .finish();
-
-            store
-                .insert_raw(&cookie, &Url::parse("https://bilibili.com/").unwrap())
-                .unwrap();
-        }
+        insert_cookies(&mut store, cookie_info).unwrap();
     }
 
     fn get_cookie(&self, name: &str) -> String {
@@ -607,6 +612,24 @@ impl Credential {
     }
 }
 
+/// 把登录信息中的 cookie 加入 `store`，对 bilibili.com 的所有子域名生效
+pub(crate) fn insert_cookies(store: &mut CookieStore, cookie_info: &Value) -> Result<()> {
+    let url = Url::parse("https://bilibili.com/").unwrap();
+    for cookie in cookie_info["cookies"]
+        .as_array()
+        .ok_or("cookie_info missing cookies")?
+    {
+        let (Some(name), Some(value)) = (cookie["name"].as_str(), cookie["value"].as_str()) else {
+            return Err(Kind::Custom(format!("invalid cookie: {cookie}")));
+        };
+        let cookie = Cookie::build(name, value).domain("bilibili.com").finish();
+        store
+            .insert_raw(&cookie, &url)
+            .map_err(|e| Kind::Custom(e.to_string()))?;
+    }
+    Ok(())
+}
+
 impl Default for Credential {
     fn default() -> Self {
         Self::new()
@@ -0,0 +1,253 @@
+use crate::error::{Kind, Result};
+use crate::uploader::bilibili::{Studio, Video};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+
+/// 对已有稿件的修改，未设置的项保持不变，可从命令行参数或 YAML 文件读取。
+/// 分P的序号均为修改前的序号，从 1 开始
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(default)]
+pub struct StudioEdit {
+    pub title: Option<String>,
+    pub desc: Option<String>,
+    /// 逗号分隔多个tag
+    pub tag: Option<String>,
+    /// 封面地址，本地文件需先通过 [`BiliBili::cover_up`](crate::uploader::bilibili::BiliBili::cover_up) 上传
+    pub cover: Option<String>,
+    pub dtime: Option<u32>,
+    /// 1-自制 2-转载
+    pub copyright: Option<u8>,
+    /// 分P标题
+    pub part_titles: BTreeMap<usize, String>,
+    /// 删除的分P
+    pub remove: Vec<usize>,
+    /// 分P的新顺序，未列出的分P按原顺序排在后面
+    pub order: Vec<usize>,
+}
+
+impl StudioEdit {
+    /// 用 `other` 中设置的项覆盖当前的修改，命令行参数覆盖配置文件时使用
+    pub fn merge(mut self, other: StudioEdit) -> Self {
+        self.title = other.title.or(self.title);
+        self.desc = other.desc.or(self.desc);
+        self.tag = other.tag.or(self.tag);
+        self.cover = other.cover.or(self.cover);
+        self.dtime = other.dtime.or(self.dtime);
+        self.copyright = other.copyright.or(self.copyright);
+        self.part_titles.extend(other.part_titles);
+        if !other.remove.is_empty() {
+            self.remove = other.remove;
+        }
+        if !other.order.is_empty() {
+            self.order = other.order;
+        }
+        self
+    }
+
+    pub fn apply(&self, studio: &mut Studio) -> Result<()> {
+        let parts = studio.videos.len();
+        let check = |index: usize| {
+            if (1..=parts).contains(&index) {
+                Ok(())
+            } else {
+                Err(Kind::Custom(format!(
+                    "part {index} out of range, the archive has {parts} parts"
+                )))
+            }
+        };
+        let mut seen = HashSet::new();
+        for &index in &self.order {
+            check(index)?;
+            if !seen.insert(index) {
+                return Err(Kind::Custom(format!("part {index} appears twice in order")));
+            }
+        }
+        for &index in self.part_titles.keys().chain(&self.remove) {
+            check(index)?;
+        }
+        let removed: HashSet<_> = self.remove.iter().copied().collect();
+        if removed.len() == parts {
+            return Err(Kind::Custom(
+                "cannot remove every part of an archive".into(),
+            ));
+        }
+
+        if let Some(title) = &self.title {
+            studio.title = title.clone();
+        }
+        if let Some(desc) = &self.desc {
+            studio.desc = desc.clone();
+        }
+        if let Some(tag) = &self.tag {
+            studio.tag = tag.clone();
+        }
+        if let Some(cover) = &self.cover {
+            studio.cover = cover.clone();
+        }
+        if let Some(dtime) = self.dtime {
+            studio.dtime = Some(dtime);
+        }
+        if let Some(copyright) = self.copyright {
+            studio.copyright = copyright;
+        }
+
+        let mut videos: Vec<Option<Video>> = std::mem::take(&mut studio.videos)
+            .into_iter()
+            .map(Some)
+            .collect();
+        for (&index, title) in &self.part_titles {
+            if let Some(video) = &mut videos[index - 1] {
+                video.title = Some(title.clone());
+            }
+        }
+        let order = self
+            .order
+            .iter()
+            .copied()
+            .chain((1..=parts).filter(|index| !seen.contains(index)));
+        studio.videos = order
+            .filter(|index| !removed.contains(index))
+            .filter_map(|index| videos[index - 1].take())
+            .collect();
+        Ok(())
+    }
+}
+
+/// 比较修改前后的稿件，每行一项，`-` 为修改前，`+` 为修改后
+pub fn studio_diff(before: &Studio, after: &Studio) -> Result<Vec<String>> {
+    let before = serde_json::to_value(before)?;
+    let after = serde_json::to_value(after)?;
+    let mut lines = Vec::new();
+    if let (Value::Object(before), Value::Object(after)) = (&before, &after) {
+        for (key, value) in after {
+            let old = before.get(key).unwrap_or(&Value::Null);
+            if key != "videos" && old != value {
+                lines.push(format!("- {key}: {old}"));
+                lines.push(format!("+ {key}: {value}"));
+            }
+        }
+    }
+    if before["videos"] != after["videos"] {
+        let parts = |videos: &Value| -> Vec<String> {
+            videos
+                .as_array()
+                .into_iter()
+                .flatten()
+                .enumerate()
+                .map(|(i, video)| {
+                    format!(
+                        "P{} {} ({})",
+                        i + 1,
+                        video["title"].as_str().unwrap_or_default(),
+                        video["filename"].as_str().unwrap_or_default()
+                    )
+                })
+                .collect()
+        };
+        lines.extend(parts(&before["videos"]).iter().map(|p| format!("- {p}")));
+        lines.extend(parts(&after["videos"]).iter().map(|p| format!("+ {p}")));
+    }
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn studio(parts: usize) -> Studio {
+        Studio::builder()
+            .title("title")
+            .desc_v2(None)
+            .videos(
+                (1..=parts)
+                    .map(|i| {
+                        let mut video = Video::new(&format!("n{i}"));
+                        video.title = Some(format!("P{i}"));
+                        video
+                    })
+                    .collect(),
+            )
+            .build()
+    }
+
+    fn filenames(studio: &Studio) -> Vec<&str> {
+        studio.videos.iter().map(|v| v.filename.as_str()).collect()
+    }
+
+    #[test]
+    fn reorders_renames_and_removes_parts() -> Result<()> {
+        let mut studio = studio(4);
+        let edit = StudioEdit {
+            title: Some("new".into()),
+            part_titles: BTreeMap::from([(3, "third".into())]),
+            remove: vec![2],
+            order: vec![3, 1],
+            ..Default::default()
+        };
+        edit.apply(&mut studio)?;
+        assert_eq!(studio.title, "new");
+        assert_eq!(filenames(&studio), ["n3", "n1", "n4"]);
+        assert_eq!(studio.videos[0].title.as_deref(), Some("third"));
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_parts() {
+        let edit = |edit: StudioEdit| edit.apply(&mut studio(2));
+        assert!(edit(StudioEdit {
+            remove: vec![3],
+            ..Default::default()
+        })
+        .is_err());
+        assert!(edit(StudioEdit {
+            order: vec![1, 1],
+            ..Default::default()
+        })
+        .is_err());
+        assert!(edit(StudioEdit {
+            remove: vec![1, 2],
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn flags_override_config() -> Result<()> {
+        let config: StudioEdit =
+            serde_yaml::from_str("title: a\ndesc: b\npart_titles:\n  1: c\nremove: [2]")?;
+        let edit = config.merge(StudioEdit {
+            title: Some("d".into()),
+            part_titles: BTreeMap::from([(2, "e".into())]),
+            ..Default::default()
+        });
+        assert_eq!(edit.title.as_deref(), Some("d"));
+        assert_eq!(edit.desc.as_deref(), Some("b"));
+        assert_eq!(edit.part_titles.len(), 2);
+        assert_eq!(edit.remove, [2]);
+        Ok(())
+    }
+
+    #[test]
+    fn diff_lists_changes() -> Result<()> {
+        let before = studio(2);
+        let mut after = before.clone();
+        StudioEdit {
+            tag: Some("录播".into()),
+            remove: vec![1],
+            ..Default::default()
+        }
+        .apply(&mut after)?;
+        assert_eq!(
+            studio_diff(&before, &after)?,
+            [
+                "- tag: \"\"",
+                "+ tag: \"录播\"",
+                "- P1 P1 (n1)",
+                "- P2 P2 (n2)",
+                "+ P1 P2 (n2)",
+            ]
+        );
+        Ok(())
+    }
+}
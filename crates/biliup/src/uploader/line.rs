@@ -1,23 +1,29 @@
 use crate::error::Result;
+use crate::uploader::chunk_limiter::ChunkLimiter;
+use crate::uploader::observer::{ChunkEvents, UploadEvent, UploadObserver};
+use crate::uploader::rate_limiter::RateLimiter;
 use crate::uploader::{Uploader, VideoFile, VideoStream};
-use futures::{Stream, TryStreamExt};
+use futures::future::{join_all, Either};
+use futures::{Stream, StreamExt, TryStreamExt};
 use reqwest::{Body, RequestBuilder};
 
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::ffi::OsStr;
 
 use crate::client::StatelessClient;
 use crate::error::Kind::Custom;
 use crate::uploader::bilibili::{BiliBili, Video};
-// use crate::uploader::line::cos::Cos;
-// use crate::uploader::line::kodo::Kodo;
-use crate::uploader::line::upos::Upos;
-use std::time::Instant;
-use tracing::info;
-
-// pub mod cos;
-// pub mod kodo;
+use crate::uploader::line::cos::Cos;
+use crate::uploader::line::kodo::Kodo;
+use crate::uploader::line::upos::{UploadState, Upos};
+use std::collections::{BTreeMap, HashMap};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+pub mod cos;
+pub mod kodo;
 pub mod upos;
 
 pub struct Parcel {
@@ -27,77 +33,173 @@ pub struct Parcel {
 }
 
 impl Parcel {
-    pub async fn upload<F, S, B>(
+    /// `chunk_limiter` 为空时每个文件单独限制为 `limit` 个分块
+    pub async fn upload(
         self,
         client: StatelessClient,
         limit: usize,
-        progress: F,
-    ) -> Result<Video>
-    where
-        F: FnOnce(VideoStream) -> S,
-        S: Stream<Item = Result<(B, usize)>>,
-        B: Into<Body> + Clone,
-    {
+        rate_limiter: Option<RateLimiter>,
+        chunk_limiter: Option<&ChunkLimiter>,
+        observer: &dyn UploadObserver,
+    ) -> Result<Video> {
+        // 并发数为 0 时分块永远不会被上传
+        let limit = limit.max(1);
+        let mut video_file = self.video_file;
+        let file_name = video_file.file_name.clone();
+        let path = video_file.filepath.to_string_lossy().into_owned();
+        observer.on_event(UploadEvent::PreUpload {
+            file_name: file_name.clone(),
+            path: path.clone(),
+            total_size: video_file.size(),
+        });
+        let events = ChunkEvents::new(observer, &file_name, &path);
+        let chunk_limiter = chunk_limiter
+            .cloned()
+            .unwrap_or_else(|| ChunkLimiter::new(limit));
+        let mut digests = None;
+        let mut chunks = |vs: VideoStream| {
+            digests = Some(vs.digests());
+            let stream = chunk_limiter.limit(vs.map(|chunk| {
+                let chunk = chunk?;
+                let len = chunk.len();
+                Ok((chunk, len))
+            }));
+            match &rate_limiter {
+                Some(rate_limiter) => Either::Left(rate_limiter.throttle(stream)),
+                None => Either::Right(stream),
+            }
+        };
         let mut video = match self.line {
-            // Bucket::Cos(bucket, enable_internal) => {
-            //     // let bucket = self.pre_upload(client).await?;
-            //     let cos_client = Cos::form_post(client, bucket).await?;
-            //     let chunk_size = 10485760;
-            //     let parts = cos_client
-            //         .upload_stream(
-            //             progress(self.video_file.get_stream(chunk_size)?),
-            //             self.video_file.total_size,
-            //             limit,
-            //             enable_internal,
-            //         )
-            //         .await?;
-            //     cos_client.merge_files(parts).await?
-            // }
-            // Bucket::Kodo(bucket) => {
-            //     // let bucket = self.pre_upload(client).await?;
-            //     let chunk_size = 4194304;
-            //     Kodo::from(client, bucket)
-            //         .await?
-            //         .upload_stream(
-            //             progress(self.video_file.get_stream(chunk_size)?),
-            //             self.video_file.total_size,
-            //             limit,
-            //         )
-            //         .await?
-            // }
-            Bucket::Upos(bucket) => {
-                // let bucket: crate::uploader::upos::Bucket = self.pre_upload(client).await?;
-                let chunk_size = bucket.chunk_size;
-                let upos = Upos::from(client, bucket).await?;
-                let mut parts = Vec::new();
-                let stream = upos
+            Bucket::Cos(bucket, enable_internal) => {
+                let cos_client = Cos::form_post(client, bucket).await?;
+                let chunk_size = 10485760;
+                let parts = cos_client
                     .upload_stream(
-                        progress(self.video_file.get_stream(chunk_size)?),
-                        self.video_file.total_size,
+                        chunks(video_file.get_stream(chunk_size)?),
+                        video_file.total_size,
                         limit,
+                        enable_internal,
+                        &events,
                     )
                     .await?;
-                tokio::pin!(stream);
-                while let Some((part, _size)) = stream.try_next().await? {
-                    parts.push(part);
-                }
-                upos.get_ret_video_info(&parts, &self.video_file.filepath)
+                cos_client.merge_files(parts).await?
+            }
+            Bucket::Kodo(bucket) => {
+                let chunk_size = 4194304;
+                Kodo::from(client, bucket)
+                    .await?
+                    .upload_stream(
+                        chunks(video_file.get_stream(chunk_size)?),
+                        video_file.total_size,
+                        limit,
+                        &events,
+                    )
                     .await?
             }
+            Bucket::Upos(bucket) => {
+                // let bucket: crate::uploader::upos::Bucket = self.pre_upload(client).await?;
+                let upos = Upos::from(client, bucket).await?;
+                upload_upos(
+                    upos,
+                    &mut video_file,
+                    BTreeMap::new(),
+                    limit,
+                    chunks,
+                    &events,
+                )
+                .await?
+            }
+            Bucket::UposResume(state) => {
+                info!(
+                    "resume {}: {} parts uploaded",
+                    video_file.file_name,
+                    state.parts.len()
+                );
+                let upos = Upos::resume(client, state.bucket, state.upload_id);
+                upload_upos(upos, &mut video_file, state.parts, limit, chunks, &events).await?
+            }
         };
 
+        video.md5 = digests.map(|digests| digests.file());
         if video.title.is_none() {
-            video.title = self
-                .video_file
-                .filepath
-                .file_stem()
-                .and_then(OsStr::to_str)
-                .map(|s| s.to_string())
+            video.title = VideoFile::default_title(&video_file.filepath)
         };
+        observer.on_event(UploadEvent::FileCompleted {
+            file_name,
+            path,
+            filename: video.filename.clone(),
+            md5: video.md5.clone(),
+        });
         Ok(video)
     }
 }
 
+/// 上传过程中每收到一个分块确认就更新状态文件，完成后删除
+async fn upload_upos<F, S, B>(
+    upos: Upos,
+    video_file: &mut VideoFile,
+    mut uploaded: BTreeMap<usize, String>,
+    limit: usize,
+    chunks: F,
+    events: &ChunkEvents<'_>,
+) -> Result<Video>
+where
+    F: FnOnce(VideoStream) -> S,
+    S: Stream<Item = Result<(B, usize)>>,
+    B: Into<Body> + Clone,
+{
+    let chunk_size = upos.chunk_size();
+    let total_size = video_file.total_size;
+    let resumable = video_file.is_resumable();
+    let size = video_file.size();
+    let video_stream = video_file.get_stream(chunk_size)?;
+    let path = &video_file.filepath;
+    let skip = uploaded.clone();
+    if resumable {
+        upos.state(total_size, uploaded.clone()).save(path)?;
+    }
+    let digests = video_stream.digests();
+    let stream = upos
+        .upload_stream(chunks(video_stream), size, limit, &skip, &digests, events)
+        .await?;
+    tokio::pin!(stream);
+    while let Some((part, _size)) = stream.try_next().await? {
+        if let (Some(part_number), Some(etag)) =
+            (part["partNumber"].as_u64(), part["eTag"].as_str())
+        {
+            uploaded.insert(part_number as usize, etag.to_string());
+            if resumable {
+                upos.state(total_size, uploaded.clone()).save(path)?;
+            }
+        }
+    }
+    let parts: Vec<_> = uploaded
+        .iter()
+        .map(|(part_number, etag)| json!({"partNumber": part_number, "eTag": etag}))
+        .collect();
+    let video = upos.get_ret_video_info(&parts, path).await?;
+    if resumable {
+        UploadState::remove(path)?;
+    }
+    Ok(video)
+}
+
+/// bilibili 返回的地址通常省略了协议，如 `//upos-cs-upcdnbda2.bilivideo.com`
+pub(crate) fn with_scheme(url: &str) -> String {
+    if url.starts_with("//") {
+        format!("https:{url}")
+    } else {
+        url.to_string()
+    }
+}
+
+/// 单条线路的探测超时
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// 测速时上传的数据大小
+const PROBE_SAMPLE_SIZE: usize = 1024 * 1024;
+/// 探测结果在磁盘上的默认缓存时间
+pub const PROBE_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct Probe {
     #[serde(rename = "OK")]
@@ -108,52 +210,241 @@ pub struct Probe {
 
 impl Probe {
     pub async fn probe(client: &reqwest::Client) -> Result<Line> {
+        Ok(Self::rank(client)
+            .await?
+            .into_iter()
+            .next()
+            .unwrap_or_default())
+    }
+
+    /// 并发测速所有线路，按吞吐量从高到低排序，超时或失败的线路不会出现在结果中
+    pub async fn rank(client: &reqwest::Client) -> Result<Vec<Line>> {
         let res: Self = client
             .get("https://member.bilibili.com/preupload?r=probe")
             .send()
             .await?
             .json()
             .await?;
-        // let client = res.ping(client);
-        let mut choice_line: Line = Default::default();
-        for mut line in res.lines {
+        Ok(res.measure(client, PROBE_TIMEOUT).await)
+    }
+
+    /// 优先使用 `path` 处未过期的探测结果，否则重新探测并写入缓存
+    pub async fn rank_cached(
+        client: &reqwest::Client,
+        path: &Path,
+        ttl: Duration,
+    ) -> Result<Vec<Line>> {
+        match Self::load_cache(path, ttl) {
+            Ok(Some(lines)) => {
+                info!("using cached probe result {}", path.display());
+                return Ok(lines);
+            }
+            Ok(None) => {}
+            Err(e) => warn!("unable to read probe cache {}: {e}", path.display()),
+        }
+        let lines = Self::rank(client).await?;
+        if !lines.is_empty() {
+            if let Err(e) = std::fs::write(path, serde_json::to_vec(&lines)?) {
+                warn!("unable to write probe cache {}: {e}", path.display());
+            }
+        }
+        Ok(lines)
+    }
+
+    /// 默认的探测结果缓存文件
+    pub fn cache_file() -> PathBuf {
+        std::env::temp_dir().join("biliup_probe.json")
+    }
+
+    fn load_cache(path: &Path, ttl: Duration) -> Result<Option<Vec<Line>>> {
+        let modified = match std::fs::metadata(path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if modified.elapsed().map_or(true, |elapsed| elapsed > ttl) {
+            return Ok(None);
+        }
+        let lines: Vec<Line> = serde_json::from_slice(&std::fs::read(path)?)?;
+        Ok(Some(lines).filter(|lines| !lines.is_empty()))
+    }
+
+    async fn measure(self, client: &reqwest::Client, timeout: Duration) -> Vec<Line> {
+        let probe = &self.probe;
+        let pings = self.lines.into_iter().map(|mut line| async move {
             let instant = Instant::now();
-            if Probe::ping(&res.probe, &format!("https:{}", line.probe_url), client)
-                .send()
-                .await?
-                .status()
-                .is_success()
-            {
-                line.cost = instant.elapsed().as_millis();
-                info!("{}: {}", line.query, line.cost);
-                if choice_line.cost > line.cost {
-                    choice_line = line
+            let url = with_scheme(&line.probe_url);
+            match tokio::time::timeout(timeout, Probe::ping(probe, &url, client).send()).await {
+                Ok(Ok(response)) if response.status().is_success() => {
+                    line.cost = instant.elapsed().as_millis().max(1);
+                    info!(
+                        "{}: {}ms, {:.2} MB/s",
+                        line.query,
+                        line.cost,
+                        Probe::sample_size(probe) as f64 / 1000. / line.cost as f64
+                    );
+                    Some(line)
                 }
-            };
+                Ok(Ok(response)) => {
+                    warn!("{}: {}", line.query, response.status());
+                    None
+                }
+                Ok(Err(e)) => {
+                    warn!("{}: {e}", line.query);
+                    None
+                }
+                Err(_) => {
+                    warn!("{}: timed out", line.query);
+                    None
+                }
+            }
+        });
+        let mut lines: Vec<_> = join_all(pings).await.into_iter().flatten().collect();
+        // 所有线路上传相同大小的数据，耗时越短吞吐量越高
+        lines.sort_by_key(|line| line.cost);
+        lines
+    }
+
+    fn sample_size(probe: &serde_json::Value) -> usize {
+        if !probe["get"].is_null() {
+            0
+        } else {
+            PROBE_SAMPLE_SIZE
         }
-        Ok(choice_line)
     }
 
     fn ping(probe: &serde_json::Value, url: &str, client: &reqwest::Client) -> RequestBuilder {
         if !probe["get"].is_null() {
             client.get(url)
         } else {
-            client
-                .post(url)
-                .body(vec![0; (1024. * 0.1 * 1024.) as usize])
+            client.post(url).body(vec![0; PROBE_SAMPLE_SIZE])
         }
     }
 }
 
+/// 按探测结果排序的候选线路，当前线路 pre_upload 或上传失败时依次切换到下一条
+#[derive(Debug)]
+pub struct Failover {
+    lines: Vec<Line>,
+    resume: bool,
+}
+
+impl Failover {
+    /// 没有可用线路时使用默认线路
+    pub fn new(mut lines: Vec<Line>) -> Self {
+        if lines.is_empty() {
+            lines.push(Line::default());
+        }
+        Self {
+            lines,
+            resume: false,
+        }
+    }
+
+    /// 视频旁存在未完成的上传状态时从断点继续
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+
+    /// 本地文件在线路失败后会重新读取，其他来源只能上传一次，失败后不再切换线路
+    #[allow(clippy::too_many_arguments)]
+    pub async fn upload(
+        &self,
+        bili: &BiliBili,
+        client: StatelessClient,
+        video_file: VideoFile,
+        limit: usize,
+        rate_limiter: Option<RateLimiter>,
+        chunk_limiter: Option<&ChunkLimiter>,
+        observer: &dyn UploadObserver,
+    ) -> Result<Video> {
+        let mut video_file = Some(video_file);
+        // 有断点时先在第一条线路上续传，之后与没有断点时一样依次尝试所有线路
+        let state = video_file
+            .as_ref()
+            .filter(|_| self.resume)
+            .and_then(saved_state);
+        let attempts = state
+            .map(|state| (&self.lines[0], Some(state)))
+            .into_iter()
+            .chain(self.lines.iter().map(|line| (line, None)));
+        let mut last_error = None;
+        for (line, state) in attempts {
+            let Some(current) = video_file.take() else {
+                break;
+            };
+            let resuming = state.is_some();
+            let bucket = match state {
+                Some(state) => Bucket::UposResume(state),
+                None => match line
+                    .bucket(bili, &current.file_name, current.total_size)
+                    .await
+                {
+                    Ok(bucket) => bucket,
+                    Err(e) => {
+                        warn!("pre_upload failed on {}: {e}", line.query);
+                        video_file = Some(current);
+                        last_error = Some(e);
+                        continue;
+                    }
+                },
+            };
+            let filepath = current.filepath.clone();
+            video_file = current.reopen().transpose()?;
+            let parcel = Parcel {
+                line: bucket,
+                video_file: current,
+            };
+            match parcel
+                .upload(
+                    client.clone(),
+                    limit,
+                    rate_limiter.clone(),
+                    chunk_limiter,
+                    observer,
+                )
+                .await
+            {
+                Ok(video) => return Ok(video),
+                Err(e) if resuming && UploadState::is_rejected(&e) => {
+                    warn!("saved upload was rejected, starting over: {e}");
+                    UploadState::remove(&filepath)?;
+                    last_error = Some(e);
+                }
+                Err(e) => {
+                    warn!("upload failed on {}: {e}", line.query);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Custom("no upload line available".into())))
+    }
+}
+
+impl From<Line> for Failover {
+    fn from(line: Line) -> Self {
+        Self::new(vec![line])
+    }
+}
+
 enum Bucket {
-    // Cos(cos::Bucket, bool),
-    // Kodo(kodo::Bucket),
+    Cos(cos::Bucket, bool),
+    Kodo(kodo::Bucket),
     Upos(upos::Bucket),
+    UposResume(UploadState),
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+/// 配置文件中的自定义线路只需填写 `os` 与 `query`，如
+/// `{os: upos, query: "zone=cs&upcdn=bda2&probe_version=20221109"}`
+#[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct Line {
     os: Uploader,
+    #[serde(default)]
     probe_url: String,
     query: String,
     #[serde(skip)]
@@ -162,14 +453,21 @@ pub struct Line {
 
 impl Line {
     pub async fn pre_upload(&self, bili: &BiliBili, video_file: VideoFile) -> Result<Parcel> {
-        let total_size = video_file.total_size;
-        let file_name = video_file.file_name.clone();
-        // let profile = if let Uploader::Upos = self.os {
-        //     "ugcupos/bup"
-        // } else {
-        //     "ugcupos/bupfetch"
-        // };
-        let profile = "ugcupos/bup";
+        Ok(Parcel {
+            line: self
+                .bucket(bili, &video_file.file_name, video_file.total_size)
+                .await?,
+            video_file,
+        })
+    }
+
+    /// 大小未知时（仍在写入的文件、标准输入）`size` 为 0，预上传只用它分配线路与分块大小
+    async fn bucket(&self, bili: &BiliBili, file_name: &str, total_size: u64) -> Result<Bucket> {
+        let profile = if let Uploader::Upos = self.os {
+            "ugcupos/bup"
+        } else {
+            "ugcupos/bupfetch"
+        };
         let params = json!({
             "r": self.os,
             "profile": profile,
@@ -196,28 +494,40 @@ impl Line {
             )));
         }
         match self.os {
-            Uploader::Upos => Ok(Parcel {
-                line: Bucket::Upos(response.json().await?),
-                video_file,
-            }),
-            // Uploader::Kodo => Ok(Parcel {
-            //     line: Bucket::Kodo(response.json().await?),
-            //     video_file,
-            // }),
+            Uploader::Upos => Ok(Bucket::Upos(response.json().await?)),
+            Uploader::Kodo => Ok(Bucket::Kodo(response.json().await?)),
             // Uploader::Bos | Uploader::Gcs => {
             //     panic!("unsupported")
             // }
-            // Uploader::Cos => Ok(Parcel {
-            //     line: Bucket::Cos(response.json().await?, self.probe_url == "internal"),
-            //     video_file,
-            // }),
-            // _ => {
-            //     panic!("unsupported")
-            // }
+            Uploader::Cos => Ok(Bucket::Cos(
+                response.json().await?,
+                self.probe_url == "internal",
+            )),
         }
     }
 }
 
+/// 读取视频旁未完成的上传状态，文件已变化或无法断点续传时为空
+fn saved_state(video_file: &VideoFile) -> Option<UploadState> {
+    if !video_file.is_resumable() {
+        // 仍在写入的文件大小会变化，无法与状态文件比对；非本地文件无法重新读取
+        return None;
+    }
+    match UploadState::load(&video_file.filepath) {
+        Ok(Some(state)) if state.total_size == video_file.total_size => return Some(state),
+        Ok(Some(_)) => warn!(
+            "{} has changed since last upload, starting over",
+            video_file.file_name
+        ),
+        Ok(None) => {}
+        Err(e) => warn!(
+            "unable to read upload state of {}: {e}",
+            video_file.file_name
+        ),
+    }
+    None
+}
+
 impl Default for Line {
     fn default() -> Self {
         Line {
@@ -229,14 +539,51 @@ impl Default for Line {
     }
 }
 
-// pub fn kodo() -> Line {
-//     Line {
-//         os: Uploader::Kodo,
-//         query: "bucket=bvcupcdnkodobm&probe_version=20211012".into(),
-//         probe_url: "//up-na0.qbox.me/crossdomain.xml".into(),
-//         cost: 0,
-//     }
-// }
+/// 内置线路的名称，与命令行 `--line` 的取值一致
+pub const BUILTIN_LINES: [&str; 11] = [
+    "bda2",
+    "ws",
+    "qn",
+    "bldsa",
+    "tx",
+    "txa",
+    "bda",
+    "alia",
+    "kodo",
+    "cos",
+    "cos-internal",
+];
+
+/// 按名称查找线路，自定义线路优先于同名的内置线路
+pub fn by_name(name: &str, custom: &HashMap<String, Line>) -> Option<Line> {
+    if let Some(line) = custom.get(name) {
+        return Some(line.clone());
+    }
+    let line = match name {
+        "bda2" => bda2(),
+        "ws" => ws(),
+        "qn" => qn(),
+        "bldsa" => bldsa(),
+        "tx" => tx(),
+        "txa" => txa(),
+        "bda" => bda(),
+        "alia" => alia(),
+        "kodo" => kodo(),
+        "cos" => cos(),
+        "cos-internal" => cos_internal(),
+        _ => return None,
+    };
+    Some(line)
+}
+
+pub fn kodo() -> Line {
+    Line {
+        os: Uploader::Kodo,
+        query: "bucket=bvcupcdnkodobm&probe_version=20211012".into(),
+        probe_url: "//up-na0.qbox.me/crossdomain.xml".into(),
+        cost: 0,
+    }
+}
 
 pub fn bda2() -> Line {
     Line {
@@ -265,23 +612,24 @@ pub fn qn() -> Line {
     }
 }
 
-// pub fn cos() -> Line {
-//     Line {
-//         os: Uploader::Cos,
-//         query: "&probe_version=20211012&r=cos&profile=ugcupos%2Fbupfetch&ssl=0&version=2.10.4.0&build=2100400&webVersion=2.0.0".into(),
-//         probe_url: "".into(),
-//         cost: 0,
-//     }
-// }
-
-// pub fn cos_internal() -> Line {
-//     Line {
-//         os: Uploader::Cos,
-//         query: "".into(),
-//         probe_url: "internal".into(),
-//         cost: 0,
-//     }
-// }
+pub fn cos() -> Line {
+    Line {
+        os: Uploader::Cos,
+        query: "probe_version=20211012".into(),
+        probe_url: "".into(),
+        cost: 0,
+    }
+}
+
+/// 腾讯云上海内网，仅在上海区域的腾讯云服务器上可用
+pub fn cos_internal() -> Line {
+    Line {
+        os: Uploader::Cos,
+        query: "probe_version=20211012".into(),
+        probe_url: "internal".into(),
+        cost: 0,
+    }
+}
 
 pub fn bldsa() -> Line {
     Line {
@@ -326,4 +674,477 @@ pub fn alia() -> Line {
         probe_url: "//upos-cs-upcdnalia.bilivideo.com/OK".into(),
         cost: 0,
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{
+        body_json, body_string, body_string_contains, header, method, path, query_param,
+    };
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn video_file(name: &str, len: usize) -> VideoFile {
+        let filepath = std::env::temp_dir().join(name);
+        std::fs::write(&filepath, vec![0u8; len]).unwrap();
+        VideoFile::new(&filepath).unwrap()
+    }
+
+    /// 与标准输入一样无法预知大小的数据
+    struct Unsized(io::Cursor<Vec<u8>>);
+
+    impl tokio::io::AsyncRead for Unsized {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::pin::Pin::new(&mut self.0).poll_read(cx, buf)
+        }
+    }
+
+    impl crate::uploader::source::UploadSource for Unsized {
+        fn size(&self) -> Option<u64> {
+            None
+        }
+    }
+
+    async fn upload(parcel: Parcel) -> Result<Video> {
+        parcel
+            .upload(StatelessClient::default(), 3, None, None, &|_| {})
+            .await
+    }
+
+    #[test]
+    fn with_scheme_keeps_absolute_url() {
+        assert_eq!(
+            with_scheme("//upos-cs-upcdnbda2.bilivideo.com"),
+            "https://upos-cs-upcdnbda2.bilivideo.com"
+        );
+        assert_eq!(with_scheme("http://127.0.0.1:80"), "http://127.0.0.1:80");
+    }
+
+    #[tokio::test]
+    async fn upos_upload_verifies_etag() -> Result<()> {
+        use md5::{Digest, Md5};
+
+        let server = MockServer::start().await;
+        let content: Vec<u8> = (0..1500u32).map(|i| i as u8).collect();
+        let md5 = |data: &[u8]| format!("{:x}", Md5::digest(data));
+        Mock::given(method("POST"))
+            .and(path("/ugcfx2lf/n230101.flv"))
+            .and(query_param("uploads", ""))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"upload_id": "upload_id"})),
+            )
+            .mount(&server)
+            .await;
+        // 第一次返回错误的 ETag，应当重传
+        Mock::given(method("PUT"))
+            .and(query_param("partNumber", "1"))
+            .respond_with(ResponseTemplate::new(200).insert_header("ETag", "\"mismatch\""))
+            .up_to_n_times(1)
+            .with_priority(1)
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(query_param("partNumber", "1"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("ETag", md5(&content[..1000]).as_str()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(query_param("partNumber", "2"))
+            .respond_with(
+                ResponseTemplate::new(200).insert_header("ETag", md5(&content[1000..]).as_str()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(query_param("uploadId", "upload_id"))
+            .and(body_json(json!({"parts": [
+                {"partNumber": 1, "eTag": md5(&content[..1000])},
+                {"partNumber": 2, "eTag": md5(&content[1000..])},
+            ]})))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"OK": 1})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bucket = serde_json::from_value(json!({
+            "chunk_size": 1000,
+            "auth": "auth",
+            "endpoint": server.uri(),
+            "biz_id": 1,
+            "upos_uri": "upos://ugcfx2lf/n230101.flv",
+        }))?;
+        let filepath = std::env::temp_dir().join("biliup-upos-test.flv");
+        std::fs::write(&filepath, &content)?;
+        let events = std::sync::Mutex::new(Vec::new());
+        let parcel = Parcel {
+            line: Bucket::Upos(bucket),
+            video_file: VideoFile::new(&filepath)?,
+        };
+        let video = parcel
+            .upload(StatelessClient::default(), 1, None, None, &|event| {
+                events.lock().unwrap().push(event)
+            })
+            .await?;
+        assert_eq!(video.filename, "n230101");
+        assert_eq!(video.md5, Some(md5(&content)));
+        let events: Vec<_> = events
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|event| serde_json::to_value(event).unwrap()["type"].clone())
+            .collect();
+        assert_eq!(
+            events,
+            [
+                "pre_upload",
+                "chunk_started",
+                "chunk_retried",
+                "chunk_finished",
+                "chunk_started",
+                "chunk_finished",
+                "file_completed"
+            ]
+        );
+        std::fs::remove_file(filepath)?;
+        Ok(())
+    }
+
+    /// 大小未知时每个分块只填写读到该分块为止的分块数与总大小
+    #[tokio::test]
+    async fn upos_upload_of_unknown_size() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(query_param("uploads", ""))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"upload_id": "upload_id"})),
+            )
+            .mount(&server)
+            .await;
+        for (part, size, total) in [(1, 1000, 1000), (2, 1000, 2000), (3, 500, 2500)] {
+            Mock::given(method("PUT"))
+                .and(query_param("partNumber", part.to_string()))
+                .and(query_param("chunks", part.to_string()))
+                .and(query_param("chunk", (part - 1).to_string()))
+                .and(query_param("size", size.to_string()))
+                .and(query_param("total", total.to_string()))
+                .and(query_param("start", (total - size).to_string()))
+                .and(query_param("end", total.to_string()))
+                .respond_with(ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .and(query_param("uploadId", "upload_id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"OK": 1})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bucket = serde_json::from_value(json!({
+            "chunk_size": 1000,
+            "auth": "auth",
+            "endpoint": server.uri(),
+            "biz_id": 1,
+            "upos_uri": "upos://ugcfx2lf/n230101.flv",
+        }))?;
+        let video = upload(Parcel {
+            line: Bucket::Upos(bucket),
+            video_file: VideoFile::from_source(
+                "stdin.flv",
+                Unsized(io::Cursor::new(vec![1; 2500])),
+            ),
+        })
+        .await?;
+        assert_eq!(video.filename, "n230101");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn expired_resume_is_rejected() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(query_param("uploadId", "expired"))
+            .and(query_param("partNumber", "2"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let state = UploadState {
+            total_size: 1500,
+            upload_id: "expired".into(),
+            bucket: serde_json::from_value(json!({
+                "chunk_size": 1000,
+                "auth": "auth",
+                "endpoint": server.uri(),
+                "biz_id": 1,
+                "upos_uri": "upos://ugcfx2lf/n230101.flv",
+            }))?,
+            parts: BTreeMap::from([(1, "etag1".into())]),
+        };
+        let video_file = video_file("biliup-expired-resume-test.flv", 1500);
+        let filepath = video_file.filepath.clone();
+        let e = upload(Parcel {
+            line: Bucket::UposResume(state),
+            video_file,
+        })
+        .await
+        .unwrap_err();
+        assert!(UploadState::is_rejected(&e), "{e}");
+        UploadState::remove(&filepath)?;
+        std::fs::remove_file(filepath)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn resume_reports_skipped_chunks() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .and(query_param("partNumber", "2"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(query_param("uploadId", "upload_id"))
+            .and(body_string_contains("etag1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"OK": 1})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let state = UploadState {
+            total_size: 1500,
+            upload_id: "upload_id".into(),
+            bucket: serde_json::from_value(json!({
+                "chunk_size": 1000,
+                "auth": "auth",
+                "endpoint": server.uri(),
+                "biz_id": 1,
+                "upos_uri": "upos://ugcfx2lf/n230101.flv",
+            }))?,
+            parts: BTreeMap::from([(1, "etag1".into())]),
+        };
+        let video_file = video_file("biliup-resume-progress-test.flv", 1500);
+        let filepath = video_file.filepath.clone();
+        let events = std::sync::Mutex::new(Vec::new());
+        Parcel {
+            line: Bucket::UposResume(state),
+            video_file,
+        }
+        .upload(StatelessClient::default(), 3, None, None, &|event| {
+            events.lock().unwrap().push(event)
+        })
+        .await?;
+        let finished: Vec<_> = events
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .filter_map(|event| match event {
+                UploadEvent::ChunkFinished { index, size, .. } => Some((index, size)),
+                _ => None,
+            })
+            .collect();
+        // 已确认的分块也计入进度
+        assert_eq!(finished, [(0, 1000), (1, 500)]);
+        std::fs::remove_file(filepath)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn kodo_upload() -> Result<()> {
+        let server = MockServer::start().await;
+        let auth = "UpToken uptoken";
+        Mock::given(method("POST"))
+            .and(path("/mkblk/4194304"))
+            .and(header("Authorization", auth))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ctx": "ctx1"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/mkblk/1000"))
+            .and(header("Authorization", auth))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ctx": "ctx2"})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        // key 为 base64 编码后的 `n230101.flv`
+        Mock::given(method("POST"))
+            .and(path("/mkfile/4195304/key/bjIzMDEwMS5mbHY"))
+            .and(body_string("ctx1,ctx2"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/fetch"))
+            .and(header("X-Upos-Fetch-Source", "source"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"OK": 1})))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bucket = serde_json::from_value(json!({
+            "bili_filename": "n230101",
+            "fetch_url": format!("{}/fetch", server.uri()),
+            "endpoint": server.uri(),
+            "uptoken": "uptoken",
+            "key": "n230101.flv",
+            "fetch_headers": {"X-Upos-Fetch-Source": "source"},
+        }))?;
+        let video_file = video_file("biliup-kodo-test.flv", 4194304 + 1000);
+        let filepath = video_file.filepath.clone();
+        let video = upload(Parcel {
+            line: Bucket::Kodo(bucket),
+            video_file,
+        })
+        .await?;
+        assert_eq!(video.filename, "n230101");
+        assert_eq!(video.title.as_deref(), Some("biliup-kodo-test"));
+        std::fs::remove_file(filepath)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn cos_upload() -> Result<()> {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/ugcfr/n230101.flv"))
+            .and(query_param("uploads", ""))
+            .and(header("Authorization", "post_auth"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                "<InitiateMultipartUploadResult><UploadId>upload_id</UploadId></InitiateMultipartUploadResult>",
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/ugcfr/n230101.flv"))
+            .and(query_param("uploadId", "upload_id"))
+            .and(query_param("partNumber", "1"))
+            .and(header("Authorization", "put_auth"))
+            .respond_with(ResponseTemplate::new(200).insert_header("Etag", "etag1"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/ugcfr/n230101.flv"))
+            .and(query_param("uploadId", "upload_id"))
+            .and(body_string_contains("<ETag>etag1</ETag>"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/fetch"))
+            .and(header("X-Upos-Auth", "auth"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let bucket = serde_json::from_value(json!({
+            "OK": 1,
+            "bili_filename": "n230101.flv",
+            "biz_id": 1,
+            "fetch_headers": {
+                "X-Upos-Fetch-Source": "source",
+                "X-Upos-Auth": "auth",
+                "Fetch-Header-Authorization": "authorization",
+            },
+            "fetch_url": format!("{}/fetch", server.uri()),
+            "fetch_urls": [],
+            "post_auth": "post_auth",
+            "put_auth": "put_auth",
+            "url": format!("{}/ugcfr/n230101.flv", server.uri()),
+        }))?;
+        let video_file = video_file("biliup-cos-test.flv", 1000);
+        let filepath = video_file.filepath.clone();
+        let video = upload(Parcel {
+            line: Bucket::Cos(bucket, false),
+            video_file,
+        })
+        .await?;
+        assert_eq!(video.filename, "n230101");
+        std::fs::remove_file(filepath)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn probe_ranks_lines_by_cost() {
+        let server = MockServer::start().await;
+        for (upcdn, status, delay) in [
+            ("slow", 200, 100),
+            ("fast", 200, 0),
+            ("down", 500, 0),
+            ("timeout", 200, 5000),
+        ] {
+            Mock::given(method("POST"))
+                .and(path(format!("/{upcdn}/OK")))
+                .respond_with(ResponseTemplate::new(status).set_delay(Duration::from_millis(delay)))
+                .mount(&server)
+                .await;
+        }
+        let lines: Vec<_> = ["slow", "fast", "down", "timeout"]
+            .into_iter()
+            .map(|upcdn| Line {
+                os: Uploader::Upos,
+                probe_url: format!("{}/{upcdn}/OK", server.uri()),
+                query: format!("upcdn={upcdn}"),
+                cost: 0,
+            })
+            .collect();
+        let probe = Probe {
+            ok: 1,
+            lines,
+            probe: json!({"post": {}}),
+        };
+        let lines = probe
+            .measure(&reqwest::Client::new(), Duration::from_secs(1))
+            .await;
+        let queries: Vec<_> = lines.iter().map(|line| line.query.as_str()).collect();
+        assert_eq!(queries, ["upcdn=fast", "upcdn=slow"]);
+    }
+
+    #[tokio::test]
+    async fn rank_cached_uses_fresh_cache() -> Result<()> {
+        let path = std::env::temp_dir().join("biliup-probe-cache-test.json");
+        std::fs::write(&path, serde_json::to_vec(&[qn(), ws()])?)?;
+        // 缓存未过期时不会访问网络
+        let lines = Probe::rank_cached(&reqwest::Client::new(), &path, PROBE_CACHE_TTL).await?;
+        let queries: Vec<_> = lines.iter().map(|line| line.query.as_str()).collect();
+        assert_eq!(queries, [qn().query, ws().query]);
+        assert!(Probe::load_cache(&path, Duration::ZERO)?.is_none());
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn by_name_prefers_custom_lines() {
+        let custom: HashMap<String, Line> = serde_yaml::from_str(
+            "
+            bda2: {os: upos, query: zone=cs&upcdn=bda2&probe_version=20240101}
+            mycdn: {os: kodo, query: bucket=mycdn}
+            ",
+        )
+        .unwrap();
+        let bda2 = by_name("bda2", &custom).unwrap();
+        assert_eq!(bda2.query, "zone=cs&upcdn=bda2&probe_version=20240101");
+        assert!(bda2.probe_url.is_empty());
+        let mycdn = by_name("mycdn", &custom).unwrap();
+        assert!(matches!(mycdn.os, Uploader::Kodo));
+        assert_eq!(by_name("ws", &custom).unwrap().query, ws().query);
+        assert!(by_name("unknown", &custom).is_none());
+        for name in BUILTIN_LINES {
+            assert!(by_name(name, &HashMap::new()).is_some(), "{name}");
+        }
+    }
+}
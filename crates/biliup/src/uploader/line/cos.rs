@@ -11,8 +11,9 @@ use std::collections::HashMap;
 use std::path::Path;
 
 use crate::client::StatelessClient;
-use crate::retry;
 use crate::uploader::bilibili::Video;
+use crate::uploader::line::with_scheme;
+use crate::uploader::observer::ChunkEvents;
 
 pub struct Cos {
     client: StatelessClient,
@@ -36,6 +37,7 @@ impl Cos {
         total_size: u64,
         limit: usize,
         enable_internal: bool,
+        events: &ChunkEvents<'_>,
     ) -> Result<Vec<(usize, String)>>
     where
         F: Stream<Item = Result<(B, usize)>>,
@@ -67,19 +69,26 @@ impl Cos {
                     upload_id,
                     part_number: (i + 1) as u32,
                 };
-                let response = retry(|| async {
-                    let response = client
-                        .put(url)
-                        .header(AUTHORIZATION, &self.bucket.put_auth)
-                        .header(CONTENT_LENGTH, len)
-                        .query(&params)
-                        .body(chunk.clone())
-                        .send()
-                        .await?;
-                    response.error_for_status_ref()?;
-                    Ok::<_, reqwest::Error>(response)
-                })
-                .await?;
+                events.started(i, len);
+                let response = self
+                    .client
+                    .retry_policy
+                    .retry_notify(
+                        || async {
+                            let response = client
+                                .put(url)
+                                .header(AUTHORIZATION, &self.bucket.put_auth)
+                                .header(CONTENT_LENGTH, len)
+                                .query(&params)
+                                .body(chunk.clone())
+                                .send()
+                                .await?;
+                            response.error_for_status_ref()?;
+                            Ok::<_, reqwest::Error>(response)
+                        },
+                        |attempt, e| events.retried(i, attempt, e),
+                    )
+                    .await?;
 
                 // json!({"partNumber": i + 1, "eTag": response.headers().get("Etag")})
                 let headers = response.headers();
@@ -97,6 +106,7 @@ impl Cos {
                 };
                 // etag.ok_or(anyhow!("{res}")).map(|s|s.to_str())??.to_string()
                 // let res = response.text().await?;
+                events.finished(i, len);
                 Ok::<_, Kind>((i + 1, etag))
             })
             .buffer_unordered(limit);
@@ -169,7 +179,7 @@ impl Cos {
         let res = self
             .client
             .client_with_middleware
-            .post(format!("https:{}", self.bucket.fetch_url))
+            .post(with_scheme(&self.bucket.fetch_url))
             .headers(headers)
             .send()
             .await?;
@@ -185,6 +195,8 @@ impl Cos {
                 .unwrap()
                 .into(),
             desc: "".into(),
+            md5: None,
+            subtitles: Vec::new(),
         })
     }
 }
@@ -9,8 +9,9 @@ use std::collections::HashMap;
 use std::str::FromStr;
 
 use crate::client::StatelessClient;
-use crate::retry;
 use crate::uploader::bilibili::Video;
+use crate::uploader::line::with_scheme;
+use crate::uploader::observer::ChunkEvents;
 
 pub struct Kodo {
     client: StatelessClient,
@@ -20,7 +21,7 @@ pub struct Kodo {
 
 impl Kodo {
     pub async fn from(client: StatelessClient, bucket: Bucket) -> Result<Self> {
-        let url = format!("https:{}/mkblk", bucket.endpoint); // 视频上传路径
+        let url = format!("{}/mkblk", with_scheme(&bucket.endpoint)); // 视频上传路径
         Ok(Kodo {
             client,
             bucket,
@@ -28,23 +29,25 @@ impl Kodo {
         })
     }
 
+    /// mkfile 时的文件大小以实际读取的分块为准，以便上传仍在写入的文件
     pub async fn upload_stream<F, B>(
         self,
         // file: std::fs::File,
         stream: F,
-        total_size: u64,
+        _total_size: u64,
         limit: usize,
+        events: &ChunkEvents<'_>,
         // mut process: impl FnMut(usize) -> bool,
     ) -> Result<Video>
     where
         F: Stream<Item = Result<(B, usize)>>,
         B: Into<Body> + Clone,
     {
-        // let total_size = file.metadata()?.len();
         let _chunk_size = 4194304;
         let mut parts = Vec::new();
         // let parts_cell = &RefCell::new(parts);
         let client = &self.client.client;
+        let retry_policy = &self.client.retry_policy;
         let url = &self.url;
         let uptoken = &format!("UpToken {}", &self.bucket.uptoken);
         // let stream = read_chunk(file, chunk_size, process)
@@ -55,20 +58,26 @@ impl Kodo {
                 let (chunk, len) = chunk?;
                 // let len = chunk.len();
                 // println!("{}", len);
-                let ctx: serde_json::Value = retry(|| async {
-                    let url = format!("{url}/{len}");
-                    let response = client
-                        .post(url)
-                        .header(CONTENT_LENGTH, len)
-                        .header("Authorization", header::HeaderValue::try_from(uptoken)?)
-                        .body(chunk.clone())
-                        .send()
-                        .await?;
-                    response.error_for_status_ref()?;
-                    let res = response.json().await?;
-                    Ok::<_, Kind>(res)
-                })
-                .await?;
+                events.started(i, len);
+                let ctx: serde_json::Value = retry_policy
+                    .retry_notify(
+                        || async {
+                            let url = format!("{url}/{len}");
+                            let response = client
+                                .post(url)
+                                .header(CONTENT_LENGTH, len)
+                                .header("Authorization", header::HeaderValue::try_from(uptoken)?)
+                                .body(chunk.clone())
+                                .send()
+                                .await?;
+                            response.error_for_status_ref()?;
+                            let res = response.json().await?;
+                            Ok::<_, Kind>(res)
+                        },
+                        |attempt, e| events.retried(i, attempt, e),
+                    )
+                    .await?;
+                events.finished(i, len);
 
                 Ok::<_, Kind>((
                     Ctx {
@@ -80,7 +89,9 @@ impl Kodo {
             })
             .buffer_unordered(limit);
         tokio::pin!(stream);
-        while let Some((part, _size)) = stream.try_next().await? {
+        let mut total_size = 0;
+        while let Some((part, size)) = stream.try_next().await? {
+            total_size += size;
             parts.push(part);
         }
         parts.sort_by_key(|x| x.index);
@@ -88,8 +99,8 @@ impl Kodo {
         self.client
             .client_with_middleware
             .post(format!(
-                "https:{}/mkfile/{total_size}/key/{key}",
-                self.bucket.endpoint,
+                "{}/mkfile/{total_size}/key/{key}",
+                with_scheme(&self.bucket.endpoint),
             ))
             .header("Authorization", header::HeaderValue::try_from(uptoken)?)
             .body(
@@ -110,7 +121,7 @@ impl Kodo {
         let result: serde_json::Value = self
             .client
             .client_with_middleware
-            .post(format!("https:{}", self.bucket.fetch_url))
+            .post(with_scheme(&self.bucket.fetch_url))
             .headers(headers)
             .send()
             .await?
@@ -124,6 +135,8 @@ impl Kodo {
                 title: None,
                 filename: self.bucket.bili_filename,
                 desc: "".into(),
+                md5: None,
+                subtitles: Vec::new(),
             },
         })
     }
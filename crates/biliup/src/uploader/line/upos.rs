@@ -1,19 +1,23 @@
 use crate::error::{Kind, Result};
+use crate::retry_policy::{ErrorClass, RetryableError};
 use futures::Stream;
 use futures::StreamExt;
 
-use reqwest::header::CONTENT_LENGTH;
+use reqwest::header::{CONTENT_LENGTH, ETAG};
 use reqwest::{header, Body};
 
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use crate::client::StatelessClient;
-use crate::retry;
 use crate::uploader::bilibili::Video;
+use crate::uploader::line::with_scheme;
+use crate::uploader::observer::ChunkEvents;
+use crate::uploader::Digests;
 
 pub struct Upos {
     client: StatelessClient,
@@ -22,7 +26,7 @@ pub struct Upos {
     upload_id: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Bucket {
     pub chunk_size: usize,
     auth: String,
@@ -31,6 +35,55 @@ pub struct Bucket {
     upos_uri: String,
 }
 
+/// 断点续传状态，以 `<视频文件名>.upos.json` 保存在视频文件旁
+#[derive(Serialize, Deserialize, Debug)]
+pub struct UploadState {
+    pub total_size: u64,
+    pub upload_id: String,
+    pub bucket: Bucket,
+    /// 服务器已确认的分块序号 (partNumber) 及其 ETag
+    pub parts: BTreeMap<usize, String>,
+}
+
+impl UploadState {
+    pub fn path(video_path: &Path) -> PathBuf {
+        let mut path = video_path.as_os_str().to_owned();
+        path.push(".upos.json");
+        PathBuf::from(path)
+    }
+
+    pub fn load(video_path: &Path) -> Result<Option<Self>> {
+        let path = Self::path(video_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+        Ok(Some(serde_json::from_reader(reader)?))
+    }
+
+    pub fn save(&self, video_path: &Path) -> Result<()> {
+        // 先写临时文件再重命名，避免进程中断时留下不完整的状态文件
+        let path = Self::path(video_path);
+        let mut tmp = path.clone().into_os_string();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn remove(video_path: &Path) -> Result<()> {
+        match std::fs::remove_file(Self::path(video_path)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// 上传服务器以 4xx 拒绝了续传请求，通常是 upload_id 或鉴权已过期，状态文件不能再使用
+    pub fn is_rejected(e: &Kind) -> bool {
+        e.class() == ErrorClass::ClientError
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct Protocol<'a> {
@@ -46,11 +99,7 @@ pub struct Protocol<'a> {
 
 impl Upos {
     pub async fn from(client: StatelessClient, bucket: Bucket) -> Result<Self> {
-        let url = format!(
-            "https:{}/{}",
-            bucket.endpoint,
-            bucket.upos_uri.replace("upos://", "")
-        ); // 视频上传路径
+        let url = Self::url(&bucket); // 视频上传路径
         let upload_id: serde_json::Value = client
             .client_with_middleware
             .post(format!("{url}?uploads&output=json"))
@@ -80,15 +129,53 @@ impl Upos {
         })
     }
 
-    pub async fn upload_stream<'a, F: 'a, B>(
+    /// 沿用已有的 upload_id 继续上传，不会新建分块上传任务
+    pub fn resume(client: StatelessClient, bucket: Bucket, upload_id: String) -> Self {
+        Upos {
+            client,
+            url: Self::url(&bucket),
+            bucket,
+            upload_id,
+        }
+    }
+
+    fn url(bucket: &Bucket) -> String {
+        format!(
+            "{}/{}",
+            with_scheme(&bucket.endpoint),
+            bucket.upos_uri.replace("upos://", "")
+        )
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.bucket.chunk_size
+    }
+
+    pub fn state(&self, total_size: u64, parts: BTreeMap<usize, String>) -> UploadState {
+        UploadState {
+            total_size,
+            upload_id: self.upload_id.clone(),
+            bucket: self.bucket.clone(),
+            parts,
+        }
+    }
+
+    /// `uploaded` 中的分块已被服务器确认，读取后直接跳过，只发出完成事件以便进度能够到达 100%。
+    /// 服务器返回 ETag 时与 `digests` 中分块的 MD5 比对，不一致则重传该分块。
+    /// 文件仍在写入时 `total_size` 为空，分块数与总大小只填写截至当前分块的值，
+    /// 合并分块时服务器以实际收到的分块为准
+    pub async fn upload_stream<'a, F, B>(
         &'a self,
         // file: std::fs::File,
         stream: F,
-        total_size: u64,
+        total_size: Option<u64>,
         limit: usize,
+        uploaded: &'a BTreeMap<usize, String>,
+        digests: &'a Digests,
+        events: &'a ChunkEvents<'a>,
     ) -> Result<impl Stream<Item = Result<(serde_json::Value, usize)>> + 'a>
     where
-        F: Stream<Item = Result<(B, usize)>>,
+        F: Stream<Item = Result<(B, usize)>> + 'a,
         B: Into<Body> + Clone,
     {
         // let mut parts = Vec::new();
@@ -97,7 +184,9 @@ impl Upos {
         // let parts = Vec::new();
         // let parts_cell = &RefCell::new(parts);
         let chunk_size = self.bucket.chunk_size;
-        let chunks_num = (total_size as f64 / chunk_size as f64).ceil() as usize; // 获取分块数量
+        // 获取分块数量
+        let chunks_num =
+            total_size.map(|total_size| (total_size as f64 / chunk_size as f64).ceil() as usize);
         // let file = tokio::io::BufReader::with_capacity(chunk_size, file);
         let client = &self.client.client;
         let url = &self.url;
@@ -105,39 +194,74 @@ impl Upos {
         let stream = stream
             // let mut chunks = read_chunk(file, chunk_size)
             .enumerate()
+            .filter(move |(i, chunk)| {
+                let skipped = match chunk {
+                    Ok((_, len)) if uploaded.contains_key(&(i + 1)) => {
+                        events.finished(*i, *len);
+                        true
+                    }
+                    _ => false,
+                };
+                futures::future::ready(!skipped)
+            })
             .map(move |(i, chunk)| async move {
                 let (chunk, len) = chunk?;
                 // let len = chunk.len();
                 // println!("{}", len);
+                let start = i as u64 * chunk_size as u64;
+                let end = start + len as u64;
                 let params = Protocol {
                     upload_id,
-                    chunks: chunks_num,
-                    total: total_size,
+                    chunks: chunks_num.unwrap_or(i + 1),
+                    total: total_size.unwrap_or(end),
                     chunk: i,
                     size: len,
                     part_number: i + 1,
-                    start: i as u64 * chunk_size as u64,
-                    end: i as u64 * chunk_size as u64 + len as u64,
+                    start,
+                    end,
                 };
-                retry(|| async {
-                    let response = client
-                        .put(url)
-                        .header(
-                            "X-Upos-Auth",
-                            header::HeaderValue::from_str(&self.bucket.auth)?,
-                        )
-                        .query(&params)
-                        .timeout(Duration::from_secs(240))
-                        .header(CONTENT_LENGTH, len)
-                        .body(chunk.clone())
-                        .send()
-                        .await?;
-                    response.error_for_status()?;
-                    Ok::<_, Kind>(())
-                })
-                .await?;
-
-                Ok::<_, Kind>((json!({"partNumber": params.chunk + 1, "eTag": "etag"}), len))
+                let md5 = digests.chunk(i);
+                events.started(i, len);
+                let etag = self
+                    .client
+                    .retry_policy
+                    .retry_notify(
+                        || async {
+                            let response = client
+                                .put(url)
+                                .header(
+                                    "X-Upos-Auth",
+                                    header::HeaderValue::from_str(&self.bucket.auth)?,
+                                )
+                                .query(&params)
+                                .timeout(Duration::from_secs(240))
+                                .header(CONTENT_LENGTH, len)
+                                .body(chunk.clone())
+                                .send()
+                                .await?;
+                            let response = response.error_for_status()?;
+                            let etag = response
+                                .headers()
+                                .get(ETAG)
+                                .and_then(|etag| etag.to_str().ok())
+                                .map(|etag| etag.trim_matches('"').to_ascii_lowercase());
+                            match (&md5, etag) {
+                                (Some(md5), Some(etag)) if *md5 != etag => {
+                                    Err(Kind::Custom(format!(
+                                        "chunk {} md5 mismatch: expected {md5}, got {etag}",
+                                        params.part_number
+                                    )))
+                                }
+                                (_, etag) => Ok(etag),
+                            }
+                        },
+                        |attempt, e| events.retried(i, attempt, e),
+                    )
+                    .await?;
+                let etag = etag.unwrap_or_else(|| "etag".into());
+                events.finished(i, len);
+
+                Ok::<_, Kind>((json!({"partNumber": params.chunk + 1, "eTag": etag}), len))
             })
             .buffer_unordered(limit);
         Ok(stream)
@@ -157,20 +281,24 @@ impl Upos {
             "profile": "ugcupos/bup"
         });
         // let res: serde_json::Value = self.client.post(url).query(&value).json(&json!({"parts": *parts_cell.borrow()}))
+        let auth = header::HeaderValue::from_str(&self.bucket.auth)?;
         let res: serde_json::Value = self
             .client
-            .client_with_middleware
-            .post(&self.url)
-            .header(
-                "X-Upos-Auth",
-                header::HeaderValue::from_str(&self.bucket.auth)?,
-            )
-            .query(&value)
-            .json(&json!({ "parts": parts }))
-            .timeout(Duration::from_secs(60))
-            .send()
-            .await?
-            .json()
+            .retry_policy
+            .retry(|| async {
+                self.client
+                    .client
+                    .post(&self.url)
+                    .header("X-Upos-Auth", auth.clone())
+                    .query(&value)
+                    .json(&json!({ "parts": parts }))
+                    .timeout(Duration::from_secs(60))
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await
+            })
             .await?;
         if res["OK"] != 1 {
             return Err(Kind::Custom(res.to_string()));
@@ -184,6 +312,42 @@ impl Upos {
                 .unwrap()
                 .into(),
             desc: "".into(),
+            md5: None,
+            subtitles: Vec::new(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upload_state_round_trip() -> Result<()> {
+        let video = std::env::temp_dir().join("biliup-upload-state-test.flv");
+        assert_eq!(
+            UploadState::path(&video),
+            std::env::temp_dir().join("biliup-upload-state-test.flv.upos.json")
+        );
+        let state = UploadState {
+            total_size: 30,
+            upload_id: "upload_id".into(),
+            bucket: Bucket {
+                chunk_size: 10,
+                auth: "auth".into(),
+                endpoint: "//upos-cs-upcdnbda2.bilivideo.com".into(),
+                biz_id: 1,
+                upos_uri: "upos://ugcfx2lf/n230101.flv".into(),
+            },
+            parts: BTreeMap::from([(1, "etag1".into()), (3, "etag3".into())]),
+        };
+        state.save(&video)?;
+        let loaded = UploadState::load(&video)?.expect("state file should exist");
+        assert_eq!(loaded.upload_id, state.upload_id);
+        assert_eq!(loaded.parts, state.parts);
+        assert_eq!(loaded.bucket.chunk_size, 10);
+        UploadState::remove(&video)?;
+        assert!(UploadState::load(&video)?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,132 @@
+use crate::error::Result;
+use crate::uploader::archive::ReviewState;
+use serde::Serialize;
+use std::fmt::Display;
+
+/// 上传过程中的事件，序列化后可直接推送给前端或 Python 回调。
+/// 文件相关的事件中 `path` 为完整路径，不同目录下的同名文件以此区分
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UploadEvent {
+    /// pre_upload 完成，即将开始上传；切换线路后会再次出现，`total_size` 未知时为空
+    PreUpload {
+        file_name: String,
+        path: String,
+        total_size: Option<u64>,
+    },
+    /// 分块开始上传，`index` 从 0 开始
+    ChunkStarted {
+        file_name: String,
+        path: String,
+        index: usize,
+        size: usize,
+    },
+    /// 分块上传完成，续传时已确认的分块直接发出该事件而没有 `ChunkStarted`
+    ChunkFinished {
+        file_name: String,
+        path: String,
+        index: usize,
+        size: usize,
+    },
+    /// 分块上传失败，即将进行第 `attempt` 次重试
+    ChunkRetried {
+        file_name: String,
+        path: String,
+        index: usize,
+        attempt: usize,
+        error: String,
+    },
+    /// 文件上传完成，`filename` 为投稿时使用的服务器文件名
+    FileCompleted {
+        file_name: String,
+        path: String,
+        filename: String,
+        md5: Option<String>,
+    },
+    /// 稿件提交结果，`data` 为接口的完整返回，失败时 `error` 不为空
+    Submitted {
+        title: String,
+        data: Option<serde_json::Value>,
+        error: Option<String>,
+    },
+    /// 投稿后稿件的审核进度发生变化
+    ReviewStateChanged {
+        vid: String,
+        title: String,
+        #[serde(flatten)]
+        state: ReviewState,
+    },
+}
+
+impl UploadEvent {
+    /// `result` 为投稿或编辑稿件接口的返回
+    pub fn submitted<T: Serialize>(title: &str, result: &Result<T>) -> Self {
+        UploadEvent::Submitted {
+            title: title.into(),
+            data: result
+                .as_ref()
+                .ok()
+                .and_then(|res| serde_json::to_value(res).ok()),
+            error: result.as_ref().err().map(ToString::to_string),
+        }
+    }
+}
+
+/// 接收上传事件，回调在上传任务中同步执行，不应阻塞
+pub trait UploadObserver: Send + Sync {
+    fn on_event(&self, event: UploadEvent);
+}
+
+impl<F> UploadObserver for F
+where
+    F: Fn(UploadEvent) + Send + Sync,
+{
+    fn on_event(&self, event: UploadEvent) {
+        self(event)
+    }
+}
+
+/// 单个文件的分块事件，由各上传线路发出
+pub struct ChunkEvents<'a> {
+    observer: &'a dyn UploadObserver,
+    file_name: &'a str,
+    path: &'a str,
+}
+
+impl<'a> ChunkEvents<'a> {
+    pub fn new(observer: &'a dyn UploadObserver, file_name: &'a str, path: &'a str) -> Self {
+        Self {
+            observer,
+            file_name,
+            path,
+        }
+    }
+
+    pub fn started(&self, index: usize, size: usize) {
+        self.observer.on_event(UploadEvent::ChunkStarted {
+            file_name: self.file_name.into(),
+            path: self.path.into(),
+            index,
+            size,
+        })
+    }
+
+    pub fn finished(&self, index: usize, size: usize) {
+        self.observer.on_event(UploadEvent::ChunkFinished {
+            file_name: self.file_name.into(),
+            path: self.path.into(),
+            index,
+            size,
+        })
+    }
+
+    pub fn retried(&self, index: usize, attempt: usize, error: &impl Display) {
+        self.observer.on_event(UploadEvent::ChunkRetried {
+            file_name: self.file_name.into(),
+            path: self.path.into(),
+            index,
+            attempt,
+            error: error.to_string(),
+        })
+    }
+}
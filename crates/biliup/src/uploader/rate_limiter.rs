@@ -0,0 +1,142 @@
+use futures::{Stream, StreamExt};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// 令牌桶限速器，克隆后共享同一个桶，可用于限制多个文件同时上传时的总带宽。
+/// `bytes_per_sec` 为 0 时不限速
+#[derive(Clone, Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    bucket: Arc<Mutex<TokenBucket>>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec,
+            bucket: Arc::new(Mutex::new(TokenBucket {
+                // 桶容量为一秒的流量
+                tokens: bytes_per_sec as f64,
+                last: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// 取走 `n` 个令牌，令牌不足时等待补足。
+    /// 允许一次取走超过桶容量的令牌，欠下的部分由本次及之后的调用者等待偿还。
+    pub async fn acquire(&self, n: usize) {
+        if self.bytes_per_sec == 0 {
+            return;
+        }
+        let wait = {
+            let rate = self.bytes_per_sec as f64;
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last).as_secs_f64();
+            bucket.tokens = f64::min(rate, bucket.tokens + elapsed * rate) - n as f64;
+            bucket.last = now;
+            if bucket.tokens < 0. {
+                Duration::from_secs_f64(-bucket.tokens / rate)
+            } else {
+                Duration::ZERO
+            }
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 在分块被上传前按分块大小取令牌
+    pub fn throttle<S, B, E>(&self, stream: S) -> impl Stream<Item = Result<(B, usize), E>>
+    where
+        S: Stream<Item = Result<(B, usize), E>>,
+    {
+        let limiter = self.clone();
+        stream.then(move |chunk| {
+            let limiter = limiter.clone();
+            async move {
+                if let Ok((_, len)) = &chunk {
+                    limiter.acquire(*len).await;
+                }
+                chunk
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::stream;
+
+    #[tokio::test]
+    async fn throttle_limits_throughput() {
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(15)
+            .mount(&server)
+            .await;
+        // 连接池的空闲计时器会让暂停的时钟跳过等待网络的时间
+        let client = reqwest::Client::builder()
+            .pool_idle_timeout(None)
+            .build()
+            .unwrap();
+        let upload = |limiter: RateLimiter, n: usize| {
+            let chunks = stream::iter((0..n).map(|_| Ok::<_, ()>((vec![0u8; 8_000], 8_000))));
+            let client = &client;
+            let uri = server.uri();
+            async move {
+                limiter
+                    .throttle(chunks)
+                    .then(|chunk| {
+                        let (body, _) = chunk.unwrap();
+                        client.put(&uri).body(body).send()
+                    })
+                    .map(|response| response.unwrap().error_for_status().unwrap())
+                    .count()
+                    .await
+            }
+        };
+
+        tokio::time::pause();
+        let limiter = RateLimiter::new(40_000);
+        let instant = Instant::now();
+        assert_eq!(upload(limiter.clone(), 10).await, 10);
+        // 桶内初始有一秒的令牌，剩余 5 个分块各需等待 0.2 秒
+        let elapsed = instant.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(999) && elapsed <= Duration::from_millis(1001),
+            "{elapsed:?}"
+        );
+
+        // 空闲一秒后桶被补满，5 个分块无需等待
+        tokio::time::advance(Duration::from_secs(1)).await;
+        let instant = Instant::now();
+        assert_eq!(upload(limiter, 5).await, 5);
+        assert_eq!(instant.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn zero_rate_is_unlimited() {
+        let limiter = RateLimiter::new(0);
+        let instant = Instant::now();
+        limiter.acquire(usize::MAX).await;
+        limiter.acquire(usize::MAX).await;
+        assert!(instant.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,119 @@
+use crate::uploader::bilibili::Vid;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// 合集，对应创作中心 `x2/creative/web/seasons` 的返回
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct Season {
+    pub id: u32,
+    pub title: String,
+    pub desc: String,
+    pub cover: String,
+    /// 合集的小节，未开启小节时只有一个默认小节
+    pub sections: Vec<Section>,
+}
+
+/// 接口中合集信息与小节分开返回
+impl<'de> Deserialize<'de> for Season {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        struct Info {
+            #[serde(default)]
+            id: u32,
+            #[serde(default)]
+            title: String,
+            #[serde(default)]
+            desc: String,
+            #[serde(default)]
+            cover: String,
+        }
+        #[derive(Deserialize, Default)]
+        struct Sections {
+            #[serde(default)]
+            sections: Vec<Section>,
+        }
+        #[derive(Deserialize)]
+        struct Raw {
+            season: Info,
+            #[serde(default, deserialize_with = "nullable")]
+            sections: Sections,
+        }
+        let raw = Raw::deserialize(deserializer)?;
+        Ok(Season {
+            id: raw.season.id,
+            title: raw.season.title,
+            desc: raw.season.desc,
+            cover: raw.season.cover,
+            sections: raw.sections.sections,
+        })
+    }
+}
+
+/// 接口有时以 `null` 代替空列表或空字符串
+pub(crate) fn nullable<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Section {
+    pub id: u64,
+    #[serde(rename = "seasonId")]
+    pub season_id: u32,
+    pub title: String,
+    /// 0 为未开启小节时的默认小节
+    #[serde(rename = "type")]
+    pub section_type: u8,
+    #[serde(rename(deserialize = "epCount"))]
+    pub ep_count: u32,
+}
+
+/// 合集中的单集，`id` 为单集的编号，与稿件的 aid 不同
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Episode {
+    pub id: u64,
+    pub title: String,
+    pub aid: u64,
+    pub bvid: String,
+    pub cid: u64,
+    pub order: u32,
+}
+
+impl Episode {
+    /// 是否为稿件 `vid` 对应的单集
+    pub fn is(&self, vid: &Vid) -> bool {
+        match vid {
+            Vid::Aid(aid) => self.aid == *aid,
+            Vid::Bvid(bvid) => &self.bvid == bvid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn season_list_flattens_sections() {
+        let seasons: Vec<Season> = serde_json::from_value(json!([
+            {
+                "season": {"id": 1, "title": "录播合集", "desc": "", "cover": "c", "isEnd": 0},
+                "sections": {"sections": [
+                    {"id": 11, "seasonId": 1, "title": "正片", "type": 0, "epCount": 3}
+                ]},
+                "part_episodes": null
+            },
+            {"season": {"id": 2, "title": "空合集"}, "sections": null}
+        ]))
+        .unwrap();
+        assert_eq!(seasons[0].sections[0].id, 11);
+        assert_eq!(seasons[0].sections[0].season_id, 1);
+        assert_eq!(seasons[0].sections[0].ep_count, 3);
+        assert!(seasons[1].sections.is_empty());
+    }
+}
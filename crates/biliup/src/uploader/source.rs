@@ -0,0 +1,62 @@
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use futures::TryStreamExt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio_util::io::StreamReader;
+
+/// 本地文件之外的上传数据来源，如标准输入、HTTP 响应或内存中的数据
+pub trait UploadSource: AsyncRead + Send + Unpin + 'static {
+    /// 数据的总大小，未知时为空
+    fn size(&self) -> Option<u64>;
+}
+
+impl UploadSource for tokio::io::Stdin {
+    fn size(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl<T> UploadSource for io::Cursor<T>
+where
+    T: AsRef<[u8]> + Send + Unpin + 'static,
+{
+    fn size(&self) -> Option<u64> {
+        Some((self.get_ref().as_ref().len() as u64).saturating_sub(self.position()))
+    }
+}
+
+/// HTTP 响应体，用于直接上传对象存储等远程地址上的视频
+pub struct HttpSource {
+    reader: StreamReader<BoxStream<'static, io::Result<Bytes>>, Bytes>,
+    size: Option<u64>,
+}
+
+impl HttpSource {
+    pub fn new(response: reqwest::Response) -> Self {
+        let size = response.content_length();
+        let stream = response.bytes_stream().map_err(io::Error::other);
+        Self {
+            reader: StreamReader::new(Box::pin(stream)),
+            size,
+        }
+    }
+}
+
+impl AsyncRead for HttpSource {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.reader).poll_read(cx, buf)
+    }
+}
+
+impl UploadSource for HttpSource {
+    fn size(&self) -> Option<u64> {
+        self.size
+    }
+}
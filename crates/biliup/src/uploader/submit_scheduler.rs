@@ -0,0 +1,205 @@
+use crate::error::{Kind, Result};
+use crate::uploader::bilibili::{BiliBili, ResponseData, Studio, SubmitOption};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::time::Instant;
+use tracing::warn;
+
+/// 账号上一次提交的时间，持有锁即占用该账号的提交队列
+type AccountQueue = Arc<tokio::sync::Mutex<Option<Instant>>>;
+
+/// 按账号串行提交稿件，同一账号两次提交之间至少间隔 `min_interval`，
+/// 投稿过于频繁等限流错误会等待后重试。服务器错误时稿件可能已经提交成功，不会重试。
+/// 克隆后共享同一份状态。
+///
+/// 稿件只以引用传入，重试次数用尽后已上传的分P仍在调用方的 [`Studio`] 中，
+/// 可以稍后再次提交或通过 [`save_unsubmitted`] 保存
+#[derive(Clone, Debug)]
+pub struct SubmitScheduler {
+    accounts: Arc<Mutex<HashMap<u64, AccountQueue>>>,
+    min_interval: Duration,
+    max_retries: usize,
+    retry_delay: Option<Duration>,
+}
+
+impl Default for SubmitScheduler {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30))
+    }
+}
+
+impl SubmitScheduler {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            accounts: Default::default(),
+            min_interval,
+            max_retries: 6,
+            retry_delay: None,
+        }
+    }
+
+    /// 限流错误的最多重试次数
+    pub fn with_max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// 重试前的等待时间，默认使用 [`BiliApiError::retry_delay`](crate::error::BiliApiError::retry_delay)
+    pub fn with_retry_delay(mut self, retry_delay: Duration) -> Self {
+        self.retry_delay = Some(retry_delay);
+        self
+    }
+
+    pub fn min_interval(&self) -> Duration {
+        self.min_interval
+    }
+
+    pub async fn submit(
+        &self,
+        bili: &BiliBili,
+        studio: &Studio,
+        option: SubmitOption,
+    ) -> Result<ResponseData> {
+        self.run(bili.login_info.token_info.mid, || {
+            bili.submit_with(studio, option)
+        })
+        .await
+    }
+
+    pub async fn edit(
+        &self,
+        bili: &BiliBili,
+        studio: &Studio,
+        option: SubmitOption,
+    ) -> Result<Value> {
+        self.run(bili.login_info.token_info.mid, || {
+            bili.edit_with(studio, option)
+        })
+        .await
+    }
+
+    /// 在 `account` 的队列中执行 `request`，因限流失败时重新调用 `request`
+    pub async fn run<F, Fut, T>(&self, account: u64, mut request: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let queue = self
+            .accounts
+            .lock()
+            .unwrap()
+            .entry(account)
+            .or_default()
+            .clone();
+        // 持有锁直到提交结束，同一账号的其他提交在此排队
+        let mut last = queue.lock().await;
+        let mut retries = 0;
+        loop {
+            if let Some(last) = *last {
+                tokio::time::sleep_until(last + self.min_interval).await;
+            }
+            let result = request().await;
+            *last = Some(Instant::now());
+            match result {
+                Err(Kind::BiliApi(e)) if retries < self.max_retries && e.is_rate_limited() => {
+                    let delay = self
+                        .retry_delay
+                        .or_else(|| e.retry_delay())
+                        .unwrap_or(self.min_interval);
+                    retries += 1;
+                    warn!(
+                        "提交失败: {e}，{} 后进行第 {retries} 次重试",
+                        humantime::format_duration(delay)
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+/// 把未能提交的稿件及已上传的分P保存为 `dir` 下的 `unsubmitted-<毫秒时间戳>.json`，
+/// 之后无需重新上传即可再次提交
+pub fn save_unsubmitted(dir: &Path, studio: &Studio) -> Result<PathBuf> {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("unsubmitted-{millis}.json"));
+    std::fs::write(&path, serde_json::to_vec_pretty(studio)?)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::BiliApiError;
+    use crate::uploader::bilibili::Video;
+
+    #[tokio::test]
+    async fn serializes_account_and_retries_rate_limit() -> Result<()> {
+        let scheduler = SubmitScheduler::new(Duration::from_millis(50))
+            .with_retry_delay(Duration::from_millis(1));
+        let started = Instant::now();
+        let mut calls = Vec::new();
+        let result = scheduler
+            .run(1, || {
+                calls.push(started.elapsed());
+                let attempt = calls.len();
+                async move {
+                    if attempt == 1 {
+                        Err(BiliApiError::new(21540, "投稿过于频繁").into())
+                    } else {
+                        Ok(attempt)
+                    }
+                }
+            })
+            .await?;
+        assert_eq!(result, 2);
+        assert!(calls[1] >= Duration::from_millis(50));
+
+        // 其他账号不受影响
+        let other = Instant::now();
+        scheduler.run(2, || async { Ok(()) }).await?;
+        assert!(other.elapsed() < Duration::from_millis(50));
+
+        let failed: Result<()> = scheduler
+            .run(1, || async {
+                Err(BiliApiError::new(-101, "账号未登录").into())
+            })
+            .await;
+        assert!(failed.is_err());
+
+        // 服务器错误时稿件可能已经提交，不能重试
+        let mut attempts = 0;
+        let failed: Result<()> = scheduler
+            .run(1, || {
+                attempts += 1;
+                async { Err(BiliApiError::new(-500, "服务器错误").into()) }
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(attempts, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn saves_unsubmitted_videos() -> Result<()> {
+        let studio = Studio::builder()
+            .title("录播")
+            .desc_v2(None)
+            .videos(vec![Video::new("n230101")])
+            .build();
+        let path = save_unsubmitted(&std::env::temp_dir(), &studio)?;
+        let saved: Studio = serde_json::from_slice(&std::fs::read(&path)?)?;
+        std::fs::remove_file(path)?;
+        assert_eq!(saved.title, "录播");
+        assert_eq!(saved.videos[0].filename, "n230101");
+        Ok(())
+    }
+}
@@ -0,0 +1,342 @@
+use crate::error::{Kind, Result};
+use crate::uploader::bilibili::Video;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// 分P的字幕文件，支持 SRT、ASS/SSA 与 BCC，投稿成功后按分P上传
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct SubtitleFile {
+    /// 字幕路径，`{stem}` 会替换为视频文件名（不含扩展名），
+    /// 不含 `{stem}` 时对应同名的视频，只有一个视频时直接用于该视频
+    pub path: PathBuf,
+    /// 字幕语言，如 zh-CN、en-US
+    #[serde(default = "default_lan")]
+    pub lan: String,
+}
+
+fn default_lan() -> String {
+    "zh-CN".into()
+}
+
+impl SubtitleFile {
+    /// 找到 `video` 对应的字幕文件，`single` 表示本次只上传了这一个视频。
+    /// 指定的字幕文件不存在时报错，按 `{stem}` 找不到字幕时只输出警告
+    pub fn resolve(&self, video: &Path, single: bool) -> Result<Option<SubtitleFile>> {
+        let stem = video
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or_default();
+        let template = self.path.to_string_lossy();
+        let path = if template.contains("{stem}") {
+            let path = PathBuf::from(template.replace("{stem}", stem));
+            if !path.is_file() {
+                warn!("{} 没有找到字幕 {}", video.display(), path.display());
+                return Ok(None);
+            }
+            path
+        } else if single || self.path.file_stem().and_then(|s| s.to_str()) == Some(stem) {
+            if !self.path.is_file() {
+                return Err(Kind::Custom(format!(
+                    "字幕文件 {} 不存在",
+                    self.path.display()
+                )));
+            }
+            self.path.clone()
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(SubtitleFile {
+            path,
+            lan: self.lan.clone(),
+        }))
+    }
+}
+
+/// 为按顺序对应 `paths` 中本地文件的分P找到字幕，不含 `{stem}` 的字幕没有对应的分P时报错
+pub fn attach_subtitles(
+    videos: &mut [Video],
+    paths: &[PathBuf],
+    subtitles: &[SubtitleFile],
+) -> Result<()> {
+    let single = paths.len() == 1;
+    for subtitle in subtitles {
+        let mut found = subtitle.path.to_string_lossy().contains("{stem}");
+        for (video, path) in videos.iter_mut().zip(paths) {
+            if let Some(resolved) = subtitle.resolve(path, single)? {
+                video.subtitles.push(resolved);
+                found = true;
+            }
+        }
+        if !found {
+            return Err(Kind::Custom(format!(
+                "字幕 {} 没有同名的视频，请使用 {{stem}} 或与视频同名的字幕文件",
+                subtitle.path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// 命令行中的字幕参数，格式为 `[LAN=]PATH`
+pub fn parse_subtitle_file(s: &str) -> std::result::Result<SubtitleFile, String> {
+    let (lan, path) = match s.split_once('=') {
+        Some((lan, path))
+            if !lan.is_empty() && lan.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') =>
+        {
+            (lan.to_string(), path)
+        }
+        _ => (default_lan(), s),
+    };
+    if path.is_empty() {
+        return Err("subtitle path is empty".into());
+    }
+    Ok(SubtitleFile {
+        path: path.into(),
+        lan,
+    })
+}
+
+/// B站使用的 BCC 字幕格式
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct Bcc {
+    pub font_size: f32,
+    pub font_color: String,
+    pub background_alpha: f32,
+    pub background_color: String,
+    #[serde(rename = "Stroke")]
+    pub stroke: String,
+    pub body: Vec<BccLine>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct BccLine {
+    /// 单位为秒
+    pub from: f64,
+    pub to: f64,
+    /// 2 为底部居中
+    #[serde(default = "default_location")]
+    pub location: u8,
+    pub content: String,
+}
+
+fn default_location() -> u8 {
+    2
+}
+
+impl Bcc {
+    /// 使用网页端默认样式
+    pub fn new(body: Vec<BccLine>) -> Self {
+        Self {
+            font_size: 0.4,
+            font_color: "#FFFFFF".into(),
+            background_alpha: 0.5,
+            background_color: "#9C27B0".into(),
+            stroke: "none".into(),
+            body,
+        }
+    }
+
+    /// 按扩展名读取字幕文件
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)?;
+        let text = text.trim_start_matches('\u{feff}');
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match ext.as_str() {
+            "srt" => Self::from_srt(text),
+            "ass" | "ssa" => Self::from_ass(text),
+            "bcc" | "json" => Ok(serde_json::from_str(text)?),
+            _ => Err(Kind::Custom(format!(
+                "unsupported subtitle format: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    pub fn from_srt(text: &str) -> Result<Self> {
+        let text = text.replace("\r\n", "\n");
+        let mut body = Vec::new();
+        for block in text.split("\n\n") {
+            let mut lines = block.lines().skip_while(|line| !line.contains("-->"));
+            let Some(timing) = lines.next() else {
+                continue;
+            };
+            let (from, to) = timing
+                .split_once("-->")
+                .ok_or_else(|| Kind::Custom(format!("invalid srt timing: {timing}")))?;
+            let content: Vec<_> = lines.map(str::trim).collect();
+            body.push(BccLine {
+                from: parse_time(from)?,
+                to: parse_time(to)?,
+                location: default_location(),
+                content: content.join("\n"),
+            });
+        }
+        Ok(Self::new(body))
+    }
+
+    pub fn from_ass(text: &str) -> Result<Self> {
+        let mut body = Vec::new();
+        let mut in_events = false;
+        let mut format: Vec<String> = Vec::new();
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('[') {
+                in_events = line.eq_ignore_ascii_case("[events]");
+                continue;
+            }
+            if !in_events {
+                continue;
+            }
+            if let Some(fields) = line.strip_prefix("Format:") {
+                format = fields
+                    .split(',')
+                    .map(|field| field.trim().to_ascii_lowercase())
+                    .collect();
+            } else if let Some(fields) = line.strip_prefix("Dialogue:") {
+                // Text 为最后一列，其中可能含有逗号
+                let values: Vec<_> = fields.splitn(format.len().max(1), ',').collect();
+                let field = |name: &str| {
+                    format
+                        .iter()
+                        .position(|field| field == name)
+                        .and_then(|i| values.get(i))
+                        .map(|value| value.trim())
+                        .ok_or_else(|| Kind::Custom(format!("ass dialogue without {name}: {line}")))
+                };
+                body.push(BccLine {
+                    from: parse_time(field("start")?)?,
+                    to: parse_time(field("end")?)?,
+                    location: default_location(),
+                    content: ass_text(field("text")?),
+                });
+            }
+        }
+        body.sort_by(|a, b| a.from.total_cmp(&b.from));
+        Ok(Self::new(body))
+    }
+}
+
+/// 去掉 ASS 的样式标签，换行符转为 `\n`
+fn ass_text(text: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '{' => in_tag = true,
+            '}' => in_tag = false,
+            _ if !in_tag => out.push(c),
+            _ => {}
+        }
+    }
+    out.replace("\\N", "\n")
+        .replace("\\n", "\n")
+        .replace("\\h", " ")
+}
+
+/// 解析 `01:02:03,456`（SRT）或 `1:02:03.45`（ASS）形式的时间，单位为秒
+fn parse_time(time: &str) -> Result<f64> {
+    let invalid = || Kind::Custom(format!("invalid subtitle time: {time}"));
+    let time = time.trim().replace(',', ".");
+    let mut secs = 0.;
+    for part in time.split(':') {
+        secs = secs * 60. + part.parse::<f64>().map_err(|_| invalid())?;
+    }
+    Ok((secs * 1000.).round() / 1000.)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_srt() -> Result<()> {
+        let bcc = Bcc::from_srt(
+            "1\r\n00:00:01,000 --> 00:00:02,500\r\n第一行\r\n第二行\r\n\r\n\
+             2\r\n00:01:00,000 --> 00:01:01,000\r\nhello\r\n",
+        )?;
+        assert_eq!(
+            bcc.body,
+            [
+                BccLine {
+                    from: 1.,
+                    to: 2.5,
+                    location: 2,
+                    content: "第一行\n第二行".into(),
+                },
+                BccLine {
+                    from: 60.,
+                    to: 61.,
+                    location: 2,
+                    content: "hello".into(),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn converts_ass() -> Result<()> {
+        let bcc = Bcc::from_ass(
+            "[Script Info]\nTitle: test\n\n[Events]\n\
+             Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n\
+             Dialogue: 0,0:00:03.00,0:00:04.50,Default,,0,0,0,,{\\b1}你好,\\N世界\n\
+             Dialogue: 0,0:00:01.00,0:00:02.00,Default,,0,0,0,,first\n",
+        )?;
+        let lines: Vec<_> = bcc
+            .body
+            .iter()
+            .map(|line| (line.from, line.to, line.content.as_str()))
+            .collect();
+        assert_eq!(lines, [(1., 2., "first"), (3., 4.5, "你好,\n世界")]);
+        Ok(())
+    }
+
+    #[test]
+    fn attaches_subtitles_to_videos() -> Result<()> {
+        let dir = std::env::temp_dir().join("biliup-subtitle-test");
+        std::fs::create_dir_all(&dir)?;
+        std::fs::write(dir.join("p1.srt"), "")?;
+        std::fs::write(dir.join("other.srt"), "")?;
+        let subtitle = |path: &str| SubtitleFile {
+            path: dir.join(path),
+            lan: default_lan(),
+        };
+        let paths = [dir.join("p1.flv"), dir.join("p2.flv")];
+
+        let mut videos = vec![Video::new("p1"), Video::new("p2")];
+        attach_subtitles(&mut videos, &paths, &[subtitle("{stem}.srt")])?;
+        assert_eq!(videos[0].subtitles, [subtitle("p1.srt")]);
+        assert!(videos[1].subtitles.is_empty());
+
+        // 只有一个视频时不要求同名
+        let mut videos = vec![Video::new("p2")];
+        attach_subtitles(&mut videos, &paths[1..], &[subtitle("other.srt")])?;
+        assert_eq!(videos[0].subtitles, [subtitle("other.srt")]);
+
+        let mut videos = vec![Video::new("p1"), Video::new("p2")];
+        assert!(attach_subtitles(&mut videos, &paths, &[subtitle("other.srt")]).is_err());
+        let mut videos = vec![Video::new("p2")];
+        assert!(attach_subtitles(&mut videos, &paths[1..], &[subtitle("missing.srt")]).is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn parses_cli_argument() {
+        assert_eq!(
+            parse_subtitle_file("en-US={stem}.srt"),
+            Ok(SubtitleFile {
+                path: "{stem}.srt".into(),
+                lan: "en-US".into(),
+            })
+        );
+        assert_eq!(
+            parse_subtitle_file("/tmp/a=b.srt").map(|s| s.lan),
+            Ok("zh-CN".into())
+        );
+    }
+}
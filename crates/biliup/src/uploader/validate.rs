@@ -0,0 +1,212 @@
+use crate::error::Result;
+use crate::uploader::bilibili::Studio;
+use crate::uploader::category::ArchivePre;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// 标题的最大字数
+pub const TITLE_MAX_LEN: usize = 80;
+/// 简介的最大字数
+pub const DESC_MAX_LEN: usize = 2000;
+/// 空间动态的最大字数
+pub const DYNAMIC_MAX_LEN: usize = 233;
+/// 标签的最大个数
+pub const TAG_MAX_COUNT: usize = 12;
+/// 单个标签的最大字数
+pub const TAG_MAX_LEN: usize = 20;
+/// 定时发布时间距离提交至少 4 小时
+pub const DTIME_MIN_AHEAD: Duration = Duration::from_secs(4 * 60 * 60);
+/// 定时发布时间距离提交不超过 15 天
+pub const DTIME_MAX_AHEAD: Duration = Duration::from_secs(15 * 24 * 60 * 60);
+
+/// 投稿信息中不符合要求的一项
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// [`Studio::validate`] 发现的所有问题
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("投稿信息不符合要求: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+pub struct ValidationError(pub Vec<Issue>);
+
+impl Studio {
+    /// 提交前检查投稿信息，一次返回所有问题，避免上传完成后才因投稿信息被拒绝。
+    /// `archive_pre` 为投稿页的分区列表，为空时不检查分区是否存在
+    pub fn validate(&self, archive_pre: Option<&ArchivePre>) -> Result<()> {
+        self.validate_at(archive_pre, SystemTime::now())
+    }
+
+    fn validate_at(&self, archive_pre: Option<&ArchivePre>, now: SystemTime) -> Result<()> {
+        let mut issues = Vec::new();
+        let mut issue = |field, message: String| issues.push(Issue { field, message });
+
+        let title_len = self.title.chars().count();
+        if self.title.trim().is_empty() {
+            issue("title", "标题不能为空".into());
+        } else if title_len > TITLE_MAX_LEN {
+            issue(
+                "title",
+                format!("标题共 {title_len} 字，不能超过 {TITLE_MAX_LEN} 字"),
+            );
+        }
+        let desc_len = self.desc.chars().count();
+        if desc_len > DESC_MAX_LEN {
+            issue(
+                "desc",
+                format!("简介共 {desc_len} 字，不能超过 {DESC_MAX_LEN} 字"),
+            );
+        }
+        let dynamic_len = self.dynamic.chars().count();
+        if dynamic_len > DYNAMIC_MAX_LEN {
+            issue(
+                "dynamic",
+                format!("空间动态共 {dynamic_len} 字，不能超过 {DYNAMIC_MAX_LEN} 字"),
+            );
+        }
+
+        let tags: Vec<_> = self
+            .tag
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .collect();
+        if tags.len() > TAG_MAX_COUNT {
+            issue(
+                "tag",
+                format!("共 {} 个标签，不能超过 {TAG_MAX_COUNT} 个", tags.len()),
+            );
+        }
+        for tag in tags {
+            if tag.chars().count() > TAG_MAX_LEN {
+                issue("tag", format!("标签 {tag} 超过 {TAG_MAX_LEN} 字"));
+            }
+        }
+
+        if let Some(archive_pre) = archive_pre {
+            if archive_pre.find(self.tid).is_none() {
+                issue("tid", format!("分区 {} 不存在", self.tid));
+            }
+        }
+
+        match self.copyright {
+            1 => {}
+            2 if self.source.trim().is_empty() => {
+                issue("source", "转载稿件需要填写转载来源".into());
+            }
+            2 => {}
+            copyright => issue(
+                "copyright",
+                format!("{copyright} 无效，应为 1-自制 或 2-转载"),
+            ),
+        }
+
+        if let Some(message) = self.dtime_issue(now) {
+            issue("dtime", message);
+        }
+
+        // 本地封面在投稿前上传，需要确认文件存在
+        let cover = &self.cover;
+        if !cover.is_empty() && !cover.starts_with("http") && !Path::new(cover).is_file() {
+            issue("cover", format!("封面文件 {cover} 不存在"));
+        }
+
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationError(issues).into())
+        }
+    }
+
+    /// 上传耗时可能较长，提交前重新检查定时发布时间是否仍在允许范围内
+    pub fn validate_dtime(&self) -> Result<()> {
+        match self.dtime_issue(SystemTime::now()) {
+            Some(message) => Err(ValidationError(vec![Issue {
+                field: "dtime",
+                message,
+            }])
+            .into()),
+            None => Ok(()),
+        }
+    }
+
+    fn dtime_issue(&self, now: SystemTime) -> Option<String> {
+        let dtime = UNIX_EPOCH + Duration::from_secs(self.dtime?.into());
+        if dtime < now + DTIME_MIN_AHEAD {
+            Some("定时发布时间需晚于当前时间 4 小时以上".into())
+        } else if dtime > now + DTIME_MAX_AHEAD {
+            Some("定时发布时间不能晚于当前时间 15 天".into())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Kind;
+    use serde_json::json;
+
+    fn issues(studio: &Studio, archive_pre: &ArchivePre, now: SystemTime) -> Vec<&'static str> {
+        match studio.validate_at(Some(archive_pre), now) {
+            Ok(()) => vec![],
+            Err(Kind::Invalid(ValidationError(issues))) => {
+                issues.into_iter().map(|issue| issue.field).collect()
+            }
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    #[test]
+    fn reports_every_invalid_field() {
+        let archive_pre: ArchivePre = serde_json::from_value(json!({"typelist": [
+            {"id": 4, "name": "游戏", "children": [{"id": 17, "parent": 4, "name": "单机游戏"}, {"id": 171, "parent": 4, "name": "电子竞技"}]}
+        ]}))
+        .unwrap();
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let studio = Studio::builder()
+            .title("录播")
+            .tag("录播,直播".into())
+            .dtime(Some(1_700_000_000 + 5 * 60 * 60))
+            .desc_v2(None)
+            .videos(vec![])
+            .build();
+        assert!(issues(&studio, &archive_pre, now).is_empty());
+        // 上传两小时后定时发布时间已不足 4 小时
+        let later = now + Duration::from_secs(2 * 60 * 60);
+        assert_eq!(issues(&studio, &archive_pre, later), ["dtime"]);
+
+        let studio = Studio {
+            title: "长".repeat(TITLE_MAX_LEN + 1),
+            tag: (0..=TAG_MAX_COUNT)
+                .map(|i| i.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            tid: 4,
+            copyright: 2,
+            dtime: Some(1_700_000_000 + 60),
+            cover: "/nonexistent/cover.jpg".into(),
+            ..studio
+        };
+        assert_eq!(
+            issues(&studio, &archive_pre, now),
+            ["title", "tag", "tid", "source", "dtime", "cover"]
+        );
+        assert!(studio
+            .validate_at(None, now)
+            .unwrap_err()
+            .to_string()
+            .starts_with("投稿信息不符合要求: title: 标题共 81 字"));
+    }
+}
@@ -1,5 +1,9 @@
-use biliup::uploader::bilibili::{Studio, Vid};
-use clap::{Parser, Subcommand, ValueEnum};
+use biliup::retry_policy::RetryPolicy;
+use biliup::uploader::bilibili::{Studio, SubmitOption, Vid};
+use biliup::uploader::category::Tid;
+use biliup::uploader::edit::StudioEdit;
+use clap::{Args, Parser, Subcommand};
+use reqwest::Proxy;
 
 use std::path::PathBuf;
 
@@ -17,6 +21,21 @@ pub struct Cli {
     #[arg(short, long, default_value = "cookies.json")]
     pub user_cookie: PathBuf,
 
+    /// 自定义上传线路文件，其中 lines 下的线路可通过 --line 按名称选择，可以直接使用投稿配置文件
+    #[arg(long, value_name = "FILE")]
+    pub lines: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub retry: RetryArgs,
+
+    /// 登录、投稿与上传使用的代理，支持 http、https、socks5 与 socks5h，如 socks5h://127.0.0.1:1080
+    #[arg(long, value_name = "URL", value_parser = parse_proxy)]
+    pub proxy: Option<Proxy>,
+
+    /// 解析直播源与下载使用的代理，与 --proxy 相互独立
+    #[arg(long, value_name = "URL", value_parser = parse_proxy)]
+    pub download_proxy: Option<Proxy>,
+
     // #[arg(long, default_value = "sqlx=debug,tower_http=debug,info")]
     #[arg(long, default_value = "tower_http=debug,info")]
     pub rust_log: String,
@@ -30,14 +49,14 @@ pub enum Commands {
     Renew,
     /// 上传视频
     Upload {
-        /// 提交接口
-        #[arg(long, default_value = "client")]
-        submit: SubmitOption,
+        /// 提交接口，未指定时使用投稿配置文件中的 submit [default: client]
+        #[arg(long)]
+        submit: Option<SubmitOption>,
 
         // Optional name to operate on
         // name: Option<String>,
 
-        /// 需要上传的视频路径,若指定配置文件投稿不需要此参数
+        /// 需要上传的视频路径,若指定配置文件投稿不需要此参数。`-` 表示从标准输入读取，也可以是 http(s) 地址
         #[arg()]
         video_path: Vec<PathBuf>,
 
@@ -45,14 +64,34 @@ pub enum Commands {
         #[arg(short, long, value_name = "FILE")]
         config: Option<PathBuf>,
 
-        /// 选择上传线路
-        #[arg(short, long, value_enum)]
-        line: Option<UploadLine>,
+        /// 选择上传线路，可选 bda2, ws, qn, bldsa, tx, txa, bda, alia, kodo, cos, cos-internal 或自定义线路的名称
+        #[arg(short, long)]
+        line: Option<String>,
 
         /// 单视频文件最大并发数
-        #[arg(long, default_value = "3")]
+        #[arg(long, value_parser = positive, default_value = "3")]
         limit: usize,
 
+        /// 同时上传的文件数 [default: 1]
+        #[arg(long, value_parser = positive)]
+        files: Option<usize>,
+
+        /// 所有文件同时上传的分块总数上限 [default: limit * files]
+        #[arg(long, value_parser = positive)]
+        max_chunks: Option<usize>,
+
+        /// 从上次中断处继续上传
+        #[arg(long)]
+        resume: bool,
+
+        /// 上传限速，单位为字节每秒，支持K、M、G后缀，所有文件共享，0 为不限速
+        #[arg(long, value_parser = human_size)]
+        max_upload_rate: Option<u64>,
+
+        /// 投稿分区，可填分区 id 或子分区名称，如 171、单机游戏
+        #[arg(long, default_value = "171")]
+        tid: Tid,
+
         #[command(flatten)]
         studio: Studio,
 
@@ -70,22 +109,95 @@ pub enum Commands {
         #[arg()]
         video_path: Vec<PathBuf>,
 
-        /// 选择上传线路
-        #[arg(short, long, value_enum)]
-        line: Option<UploadLine>,
+        /// 编辑稿件的接口，app 与 client 相同
+        #[arg(long, default_value = "client")]
+        submit: SubmitOption,
+
+        /// 选择上传线路，可选 bda2, ws, qn, bldsa, tx, txa, bda, alia, kodo, cos, cos-internal 或自定义线路的名称
+        #[arg(short, long)]
+        line: Option<String>,
 
         /// 单视频文件最大并发数
-        #[arg(long, default_value = "3")]
+        #[arg(long, value_parser = positive, default_value = "3")]
         limit: usize,
 
         #[command(flatten)]
         studio: Studio,
     },
+    /// 修改已投稿件的信息，可删除或重新排列分P，提交前显示修改内容
+    Edit {
+        /// vid为稿件 av 或 bv 号
+        vid: Vid,
+
+        #[command(flatten)]
+        edit: EditArgs,
+
+        /// 编辑稿件的接口，app 与 client 相同
+        #[arg(long, default_value = "client")]
+        submit: SubmitOption,
+
+        /// 不经确认直接提交
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// 删除稿件，删除前显示稿件信息并确认；删除单个分P请使用 edit --remove
+    Delete {
+        /// vid为稿件 av 或 bv 号
+        vid: Vid,
+
+        /// 不经确认直接删除
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// 管理合集
+    Season {
+        #[command(subcommand)]
+        command: SeasonCommands,
+    },
+    /// 列出或搜索投稿分区，投稿时 --tid 可填分区 id 或名称
+    Categories {
+        /// 只列出名称或简介中含有关键词的分区与活动
+        keyword: Option<String>,
+
+        /// 列出可参与的活动与话题，投稿时通过 --mission-id 参与
+        #[arg(long)]
+        activities: bool,
+
+        /// 输出 json
+        #[arg(long)]
+        json: bool,
+    },
     /// 打印视频详情
     Show {
         /// vid为稿件 av 或 bv 号
         // #[clap()]
         vid: Vid,
+
+        /// 输出接口返回的原始 json
+        #[arg(long)]
+        json: bool,
+    },
+    /// 跟踪稿件的审核进度直到开放浏览或被退回。
+    /// 退出码：0 已开放浏览或等待定时发布，1 出错，2 被退回，3 超时
+    Watch {
+        /// vid为稿件 av 或 bv 号
+        vid: Vid,
+
+        /// 首次轮询间隔，状态不变时逐渐翻倍
+        #[arg(long, default_value = "30s")]
+        interval: humantime::Duration,
+
+        /// 最大轮询间隔
+        #[arg(long, default_value = "10m")]
+        max_interval: humantime::Duration,
+
+        /// 超时时间，默认一直等待
+        #[arg(long)]
+        timeout: Option<humantime::Duration>,
+
+        /// 刚追加分P或编辑后使用，在此时间内未见到转码或审核中的状态时不认为已开放浏览或被退回
+        #[arg(long, default_value = "0s")]
+        grace: humantime::Duration,
     },
     /// 输出flv元数据
     DumpFlv {
@@ -118,6 +230,34 @@ pub enum Commands {
         /// Port to use
         #[arg(short, long, default_value = "19159")]
         port: u16,
+
+        /// 上传限速，单位为字节每秒，支持K、M、G后缀，所有上传任务共享，0 为不限速
+        #[arg(long, value_parser = human_size)]
+        max_upload_rate: Option<u64>,
+
+        /// 同一稿件同时上传的文件数
+        #[arg(long, value_parser = positive, default_value = "1")]
+        files: usize,
+
+        /// 所有上传任务同时上传的分块总数上限
+        #[arg(long, value_parser = positive)]
+        max_chunks: Option<usize>,
+
+        /// 边录边传，分段文件创建后立即开始上传
+        #[arg(long)]
+        tail_upload: bool,
+
+        /// 投稿与追加分P使用的接口
+        #[arg(long, default_value = "client")]
+        submit: SubmitOption,
+
+        /// 同一账号两次投稿的最小间隔，投稿过于频繁时会等待后重试
+        #[arg(long, default_value = "30s")]
+        submit_interval: humantime::Duration,
+
+        /// 选择上传线路，可选 bda2, ws, qn, bldsa, tx, txa, bda, alia, kodo, cos, cos-internal 或自定义线路的名称
+        #[arg(short, long)]
+        line: Option<String>,
     },
     /// 列出所有已上传的视频
     List {
@@ -135,28 +275,174 @@ pub enum Commands {
     },
 }
 
-#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
-pub enum UploadLine {
-    Bda2,
-    Ws,
-    Qn,
-    // Kodo,
-    // Cos,
-    // CosInternal,
-    Bldsa,
-    Tx,
-    Txa,
-    Bda,
-    Alia
+#[derive(Subcommand)]
+pub enum SeasonCommands {
+    /// 列出合集与小节
+    List,
+    /// 列出小节中的单集
+    Episodes {
+        section_id: u64,
+    },
+    /// 创建合集
+    Create {
+        title: String,
+
+        /// 合集简介
+        #[arg(long, default_value_t)]
+        desc: String,
+
+        /// 合集封面，本地文件会先上传
+        #[arg(long)]
+        cover: Option<String>,
+    },
+    /// 把稿件加入合集
+    Add {
+        season_id: u32,
+
+        /// 稿件的 av 或 bv 号
+        #[arg(required = true)]
+        vids: Vec<Vid>,
+
+        /// 加入的小节，默认为合集的第一个小节
+        #[arg(long)]
+        section: Option<u64>,
+    },
+    /// 从合集中移除稿件
+    Remove {
+        season_id: u32,
+
+        /// 稿件的 av 或 bv 号
+        #[arg(required = true)]
+        vids: Vec<Vid>,
+    },
+    /// 调整小节中稿件的顺序，未列出的稿件按原顺序排在后面
+    Order {
+        section_id: u64,
+
+        /// 稿件的 av 或 bv 号
+        #[arg(required = true)]
+        vids: Vec<Vid>,
+    },
+}
+
+/// 上传与下载请求的重试策略，未指定的项使用投稿配置文件中的 retry 或默认值
+#[derive(Args)]
+pub struct RetryArgs {
+    /// 最多重试的次数 [default: 3]
+    #[arg(long)]
+    pub max_retries: Option<u32>,
+
+    /// 第一次重试前的等待时间，之后每次翻倍，如 500ms、2s [default: 2s]
+    #[arg(long)]
+    pub retry_delay: Option<humantime::Duration>,
+
+    /// 重试等待时间的上限 [default: 64s]
+    #[arg(long)]
+    pub retry_max_delay: Option<humantime::Duration>,
+
+    /// 同时重试 4xx 错误，默认只重试 5xx、超时与连接错误。`--retry-client-errors=false` 可覆盖配置文件
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    pub retry_client_errors: Option<bool>,
 }
 
-#[derive(Debug, Clone, ValueEnum)]
-pub enum SubmitOption {
-    Client,
-    App,
-    Web,
+impl RetryArgs {
+    pub fn apply(&self, mut policy: RetryPolicy) -> RetryPolicy {
+        if let Some(max_retries) = self.max_retries {
+            policy.max_retries = max_retries;
+        }
+        if let Some(delay) = self.retry_delay {
+            policy.base_delay = delay.into();
+        }
+        if let Some(max_delay) = self.retry_max_delay {
+            policy.max_delay = max_delay.into();
+        }
+        if let Some(client_errors) = self.retry_client_errors {
+            policy.client_errors = client_errors;
+        }
+        policy
+    }
 }
 
+fn parse_proxy(url: &str) -> reqwest::Result<Proxy> {
+    Proxy::all(url)
+}
+
+/// 修改稿件的命令行参数，分P序号均为修改前的序号，从 1 开始
+#[derive(Args)]
+pub struct EditArgs {
+    /// 修改内容的 YAML 文件，可包含 title、desc、tag、cover、dtime、copyright、part_titles、remove 与 order，命令行参数优先
+    #[arg(short, long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// 视频标题
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// 视频简介
+    #[arg(long)]
+    pub desc: Option<String>,
+
+    /// 视频标签，逗号分隔多个tag
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// 视频封面的本地路径
+    #[arg(long)]
+    pub cover: Option<String>,
+
+    /// 延时发布时间，距离提交大于4小时，格式为10位时间戳
+    #[arg(long)]
+    pub dtime: Option<u32>,
+
+    /// 是否转载, 1-自制 2-转载
+    #[arg(long)]
+    pub copyright: Option<u8>,
+
+    /// 修改分P标题，如 --part-title 2=第二集，可多次指定
+    #[arg(long = "part-title", value_name = "N=TITLE", value_parser = parse_part_title)]
+    pub part_titles: Vec<(usize, String)>,
+
+    /// 删除分P，逗号分隔多个序号
+    #[arg(long, value_delimiter = ',')]
+    pub remove: Vec<usize>,
+
+    /// 分P的新顺序，如 3,1,2，未列出的分P按原顺序排在后面
+    #[arg(long, value_delimiter = ',')]
+    pub order: Vec<usize>,
+}
+
+impl EditArgs {
+    pub fn into_edit(self) -> StudioEdit {
+        StudioEdit {
+            title: self.title,
+            desc: self.desc,
+            tag: self.tag,
+            cover: self.cover,
+            dtime: self.dtime,
+            copyright: self.copyright,
+            part_titles: self.part_titles.into_iter().collect(),
+            remove: self.remove,
+            order: self.order,
+        }
+    }
+}
+
+fn parse_part_title(s: &str) -> Result<(usize, String), String> {
+    let (index, title) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected N=TITLE, got {s}"))?;
+    let index = index
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid part number {index}: {e}"))?;
+    Ok((index, title.to_string()))
+}
 
 fn human_size(s: &str) -> Result<u64, String> {
     let ret = match s.as_bytes() {
@@ -165,9 +451,20 @@ fn human_size(s: &str) -> Result<u64, String> {
         [init @ .., b'G'] => parse_u8(init)? * 1000.0 * 1000.0 * 1000.0,
         init => parse_u8(init)?,
     };
+    if ret > 0.0 && ret < 1.0 {
+        return Err(format!("{s} is less than 1 byte"));
+    }
     Ok(ret as u64)
 }
 
+fn positive(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) => Err("must be greater than 0".into()),
+        Ok(n) => Ok(n),
+        Err(e) => Err(format!("{s} is not a number. {e}")),
+    }
+}
+
 fn parse_u8(string: &[u8]) -> Result<f64, String> {
     let string = String::from_utf8_lossy(string);
     string
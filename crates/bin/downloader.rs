@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use biliup::client::StatelessClient;
 use biliup::downloader::extractor::find_extractor;
 use biliup::downloader::flv_parser::{
     aac_audio_packet_header, avc_video_packet_header, header, script_data, tag_data, tag_header,
@@ -19,9 +20,9 @@ pub async fn download(
     output: String,
     split_size: Option<u64>,
     split_time: Option<humantime::Duration>,
+    client: StatelessClient,
 ) -> Result<()> {
     let segmentable = Segmentable::new(split_time.map(|t| t.into()), split_size);
-    let client = Default::default();
     if let Some(extractor) = find_extractor(url) {
         let mut site = extractor.get_site(url, client).await?;
         site.download(&output, segmentable, None).await?;
@@ -5,13 +5,23 @@ mod server;
 mod uploader;
 
 use anyhow::Result;
+use biliup::client::StatelessClient;
+use biliup::retry_policy::RetryPolicy;
+use biliup::uploader::archive::{ReviewState, WatchOptions};
+use biliup::uploader::load_lines;
+#[cfg(feature = "server")]
+use biliup::uploader::submit_scheduler::SubmitScheduler;
 use time::macros::format_description;
 
 use crate::cli::{Cli, Commands};
 use crate::downloader::{download, generate_json};
-use crate::uploader::{append, list, login, renew, show, upload_by_command, upload_by_config};
+use crate::uploader::{
+    append, categories, delete, edit_archive, find_line, list, login, renew, season, show,
+    upload_by_command, upload_by_config, watch,
+};
 
 use clap::Parser;
+use reqwest::header::HeaderMap;
 
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
@@ -42,46 +52,171 @@ async fn main() -> Result<()> {
         .with(tracing_subscriber::fmt::layer().with_timer(timer))
         .init();
 
+    let lines = cli
+        .lines
+        .as_deref()
+        .map(load_lines)
+        .transpose()?
+        .unwrap_or_default();
+    let find_line = |line: Option<String>| line.map(|line| find_line(&line, &lines)).transpose();
+    let retry_policy = cli.retry.apply(RetryPolicy::default());
+    let client = StatelessClient::new(HeaderMap::new(), cli.proxy.clone())
+        .with_retry_policy(retry_policy.clone());
+    let download_client =
+        StatelessClient::new(HeaderMap::new(), cli.download_proxy).with_retry_policy(retry_policy);
+
     match cli.command {
-        Commands::Login => login(cli.user_cookie).await?,
+        Commands::Login => login(cli.user_cookie, cli.proxy).await?,
         Commands::Renew => {
-            renew(cli.user_cookie).await?;
+            renew(cli.user_cookie, cli.proxy).await?;
         }
         Commands::Upload {
             video_path,
             config: None,
             line,
             limit,
+            files,
+            max_chunks,
+            tid,
             studio,
             submit,
-        } => upload_by_command(studio, cli.user_cookie, video_path, line, limit, submit).await?,
+            resume,
+            max_upload_rate,
+        } => {
+            upload_by_command(
+                studio,
+                tid,
+                cli.user_cookie,
+                video_path,
+                find_line(line)?,
+                limit,
+                files.unwrap_or(1),
+                max_chunks,
+                submit.unwrap_or_default(),
+                resume,
+                max_upload_rate,
+                client,
+            )
+            .await?
+        }
         Commands::Upload {
             video_path: _,
             config: Some(config),
+            line,
+            files,
+            max_chunks,
+            submit,
+            resume,
+            max_upload_rate,
             ..
-        } => upload_by_config(config, cli.user_cookie).await?,
+        } => {
+            upload_by_config(
+                config,
+                cli.user_cookie,
+                line,
+                lines.clone(),
+                files,
+                max_chunks,
+                submit,
+                resume,
+                max_upload_rate,
+                client,
+                &cli.retry,
+            )
+            .await?
+        }
         Commands::Append {
             video_path,
             vid,
             line,
             limit,
-            studio: _,
-        } => append(cli.user_cookie, vid, video_path, line, limit).await?,
-        Commands::Show { vid } => show(cli.user_cookie, vid).await?,
+            submit,
+            studio,
+        } => {
+            append(
+                cli.user_cookie,
+                vid,
+                video_path,
+                find_line(line)?,
+                limit,
+                submit,
+                studio.subtitles,
+                client,
+            )
+            .await?
+        }
+        Commands::Edit {
+            vid,
+            edit,
+            submit,
+            yes,
+        } => edit_archive(cli.user_cookie, vid, edit, submit, yes, client).await?,
+        Commands::Delete { vid, yes } => delete(cli.user_cookie, vid, yes, client).await?,
+        Commands::Season { command } => season(cli.user_cookie, command, client).await?,
+        Commands::Categories {
+            keyword,
+            activities,
+            json,
+        } => categories(cli.user_cookie, keyword, activities, json, client).await?,
+        Commands::Show { vid, json } => show(cli.user_cookie, vid, json, client).await?,
+        Commands::Watch {
+            vid,
+            interval,
+            max_interval,
+            timeout,
+            grace,
+        } => {
+            let options = WatchOptions {
+                interval: interval.into(),
+                max_interval: max_interval.into(),
+                timeout: timeout.map(Into::into),
+                grace: grace.into(),
+            };
+            let state = watch(cli.user_cookie, vid, options, client).await?;
+            std::process::exit(match state {
+                ReviewState::Published | ReviewState::Scheduled => 0,
+                ReviewState::Rejected { .. } => 2,
+                ReviewState::Transcoding | ReviewState::Reviewing => 3,
+            });
+        }
         Commands::DumpFlv { file_name } => generate_json(file_name)?,
         Commands::Download {
             url,
             output,
             split_size,
             split_time,
-        } => download(&url, output, split_size, split_time).await?,
+        } => download(&url, output, split_size, split_time, download_client).await?,
         #[cfg(feature = "server")]
-        Commands::Server { bind, port } => server::run((&bind, port)).await?,
+        Commands::Server {
+            bind,
+            port,
+            max_upload_rate,
+            files,
+            max_chunks,
+            tail_upload,
+            line,
+            submit,
+            submit_interval,
+        } => {
+            server::run(
+                (&bind, port),
+                max_upload_rate,
+                files,
+                max_chunks,
+                tail_upload,
+                find_line(line)?,
+                submit,
+                SubmitScheduler::new(submit_interval.into()),
+                client,
+                download_client,
+            )
+            .await?
+        }
         Commands::List {
             is_pubing,
             pubed,
             not_pubed,
-        } => list(cli.user_cookie, is_pubing, pubed, not_pubed).await?,
+        } => list(cli.user_cookie, is_pubing, pubed, not_pubed, client).await?,
     };
     Ok(())
 }
@@ -1,11 +1,30 @@
 use anyhow::{Context, Result};
 
+use biliup::client::StatelessClient;
 use biliup::server::api::router::ApplicationController;
+use biliup::server::core::upload_actor::UploadSettings;
 use biliup::server::infrastructure::connection_pool::ConnectionManager;
 use biliup::server::infrastructure::service_register::ServiceRegister;
+use biliup::uploader::bilibili::SubmitOption;
+use biliup::uploader::chunk_limiter::ChunkLimiter;
+use biliup::uploader::line::Line;
+use biliup::uploader::rate_limiter::RateLimiter;
+use biliup::uploader::submit_scheduler::SubmitScheduler;
 use std::net::ToSocketAddrs;
 
-pub async fn run(addr: (&str, u16)) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+pub async fn run(
+    addr: (&str, u16),
+    max_upload_rate: Option<u64>,
+    files: usize,
+    max_chunks: Option<usize>,
+    tail_upload: bool,
+    line: Option<Line>,
+    submit: SubmitOption,
+    scheduler: SubmitScheduler,
+    client: StatelessClient,
+    download_client: StatelessClient,
+) -> Result<()> {
     // let config = Arc::new(AppConfig::parse());
 
     tracing::info!("environment loaded and configuration parsed, initializing Postgres connection and running migrations...");
@@ -17,7 +36,18 @@ pub async fn run(addr: (&str, u16)) -> Result<()> {
 
     tracing::info!("migrations successfully ran, initializing axum server...");
     let addr = addr.to_socket_addrs()?.next().unwrap();
-    ApplicationController::serve(&addr, service_register)
+    let settings = UploadSettings {
+        rate_limiter: max_upload_rate.map(RateLimiter::new),
+        files,
+        chunk_limiter: max_chunks.map(ChunkLimiter::new),
+        tail: tail_upload,
+        line,
+        client,
+        submit,
+        scheduler,
+        ..Default::default()
+    };
+    ApplicationController::serve(&addr, service_register, settings, download_client)
         .await
         .context("could not initialize application routes")?;
     Ok(())
@@ -1,32 +1,38 @@
-use crate::cli::{SubmitOption, UploadLine};
+use crate::cli::{EditArgs, RetryArgs, SeasonCommands};
 use anyhow::{anyhow, Context, Result};
 use biliup::client::StatelessClient;
 use biliup::error::Kind;
-use biliup::uploader::bilibili::{BiliBili, Studio, Vid, Video};
+use biliup::uploader::archive::{ReviewState, WatchOptions};
+use biliup::uploader::bilibili::{BiliBili, ResponseData, Studio, SubmitOption, Vid, Video};
+use biliup::uploader::category::{ArchivePre, Category, Tid};
+use biliup::uploader::chunk_limiter::ChunkLimiter;
 use biliup::uploader::credential::{Credential, LoginInfo};
-use biliup::uploader::line::Probe;
-use biliup::uploader::{credential, line, load_config, VideoFile};
-use bytes::{Buf, Bytes};
-use clap::ValueEnum;
+use biliup::uploader::edit::{studio_diff, StudioEdit};
+use biliup::uploader::line::{Failover, Line, Probe, PROBE_CACHE_TTL};
+use biliup::uploader::observer::UploadEvent;
+use biliup::uploader::rate_limiter::RateLimiter;
+use biliup::uploader::season::Season;
+use biliup::uploader::submit_scheduler::SubmitScheduler;
+use biliup::uploader::subtitle::{attach_subtitles, SubtitleFile};
+use biliup::uploader::{credential, line, load_config_with, submit_scheduler, VideoFile};
 use dialoguer::theme::ColorfulTheme;
+use dialoguer::Confirm;
 use dialoguer::Input;
 use dialoguer::Select;
-use futures::{Stream, StreamExt};
+use futures::{StreamExt, TryStreamExt};
 use image::Luma;
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use qrcode::render::unicode;
 use qrcode::QrCode;
-use reqwest::Body;
-use std::ffi::OsStr;
+use reqwest::Proxy;
+use std::collections::HashMap;
 use std::io::Seek;
 use std::path::{Path, PathBuf};
-use std::pin::Pin;
-use std::task::Poll;
 use std::time::Instant;
-use tracing::{info, warn};
+use tracing::{error, info, warn};
 
-pub async fn login(user_cookie: PathBuf) -> Result<()> {
-    let client = Credential::new();
+pub async fn login(user_cookie: PathBuf, proxy: Option<Proxy>) -> Result<()> {
+    let client = Credential::with_proxy(proxy.as_ref());
     let selection = Select::with_theme(&ColorfulTheme::default())
         .with_prompt("选择一种登录方式")
         .default(1)
@@ -52,8 +58,8 @@ pub async fn login(user_cookie: PathBuf) -> Result<()> {
     Ok(())
 }
 
-pub async fn renew(user_cookie: PathBuf) -> Result<()> {
-    let client = Credential::new();
+pub async fn renew(user_cookie: PathBuf, proxy: Option<Proxy>) -> Result<()> {
+    let client = Credential::with_proxy(proxy.as_ref());
     let mut file = fopen_rw(user_cookie)?;
     let login_info: LoginInfo = serde_json::from_reader(&file)?;
     let new_info = client.renew_tokens(login_info).await?;
@@ -64,45 +70,99 @@ pub async fn renew(user_cookie: PathBuf) -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn upload_by_command(
     mut studio: Studio,
+    tid: Tid,
     user_cookie: PathBuf,
     video_path: Vec<PathBuf>,
-    line: Option<UploadLine>,
+    line: Option<Line>,
     limit: usize,
+    files: usize,
+    max_chunks: Option<usize>,
     submit: SubmitOption,
+    resume: bool,
+    max_upload_rate: Option<u64>,
+    client: StatelessClient,
 ) -> Result<()> {
-    let bili = login_by_cookies(user_cookie).await?;
-    if studio.title.is_empty() {
-        studio.title = video_path[0]
-            .file_stem()
-            .and_then(OsStr::to_str)
-            .map(|s| s.to_string())
-            .unwrap();
+    let bili = login_by_cookies(user_cookie, &client).await?;
+    let archive_pre = archive_pre(&bili).await;
+    studio.tid = tid.resolve(archive_pre.as_ref())?;
+    // 与上传后第一个分P的默认标题一致，以便上传前检查
+    if let Some(path) = video_path.first().filter(|_| studio.title.is_empty()) {
+        let path = if path.as_os_str() == "-" {
+            Path::new(VideoFile::STDIN_FILE_NAME)
+        } else {
+            path
+        };
+        studio.title = VideoFile::default_title(path).unwrap_or_default();
     }
+    validate(archive_pre.as_ref(), [&studio])?;
     cover_up(&mut studio, &bili).await?;
-    studio.videos = upload(&video_path, &bili, line, limit).await?;
-
-    // if studio.submit_by_app {
-    //     bili.submit_by_app(&studio).await?;
-    // }
-    // else {
-    //     bili.submit(&studio).await?;
-    // }
-    // 说不定会适配 web 呢...?
-    match submit {
-        SubmitOption::App => bili.submit_by_app(&studio).await?,
-        _ => bili.submit(&studio).await?,
-    };
+    let rate_limiter = max_upload_rate.map(RateLimiter::new);
+    studio.videos = upload(
+        &video_path,
+        &bili,
+        line,
+        limit,
+        files,
+        max_chunks,
+        resume,
+        rate_limiter,
+        client,
+    )
+    .await?;
+    attach_subtitles(&mut studio.videos, &video_path, &studio.subtitles)?;
+
+    studio
+        .validate_dtime()
+        .inspect_err(|_| save_unsubmitted(Some(&studio), &studio.videos))?;
+    let ret = SubmitScheduler::default()
+        .submit(&bili, &studio, submit)
+        .await
+        .inspect_err(|_| save_unsubmitted(Some(&studio), &studio.videos))?;
+    after_submit(&bili, &ret, &studio).await?;
 
     Ok(())
 }
 
-pub async fn upload_by_config(config: PathBuf, user_cookie: PathBuf) -> Result<()> {
+#[allow(clippy::too_many_arguments)]
+pub async fn upload_by_config(
+    config: PathBuf,
+    user_cookie: PathBuf,
+    line: Option<String>,
+    mut lines: HashMap<String, Line>,
+    files: Option<usize>,
+    max_chunks: Option<usize>,
+    submit: Option<SubmitOption>,
+    resume: bool,
+    max_upload_rate: Option<u64>,
+    client: StatelessClient,
+    retry: &RetryArgs,
+) -> Result<()> {
     // println!("number of concurrent futures: {limit}");
-    let bilibili = login_by_cookies(user_cookie).await?;
-    let config = load_config(&config)?;
-    for (filename_patterns, mut studio) in config.streamers {
+    let bilibili = login_by_cookies(user_cookie, &client).await?;
+    let archive_pre = archive_pre(&bilibili).await;
+    let config = load_config_with(&config, archive_pre.as_ref())?;
+    let client = client.with_retry_policy(retry.apply(config.retry.unwrap_or_default()));
+    let rate_limiter = max_upload_rate
+        .or(config.max_upload_rate)
+        .map(RateLimiter::new);
+    let files = files.unwrap_or(config.files);
+    let max_chunks = max_chunks.or(config.max_chunks);
+    let submit = submit.or(config.submit).unwrap_or_default();
+    let scheduler = config
+        .submit_interval
+        .map(SubmitScheduler::new)
+        .unwrap_or_default();
+    // 配置文件中的线路优先于 --lines 中的同名线路
+    lines.extend(config.lines);
+    let line = line
+        .or(config.line)
+        .map(|line| find_line(&line, &lines))
+        .transpose()?;
+    let mut streamers = Vec::new();
+    for (filename_patterns, studio) in config.streamers {
         let mut paths = Vec::new();
         for entry in glob::glob(&filename_patterns)?.filter_map(Result::ok) {
             paths.push(entry);
@@ -111,51 +171,338 @@ pub async fn upload_by_config(config: PathBuf, user_cookie: PathBuf) -> Result<(
             warn!("未搜索到匹配的视频文件：{filename_patterns}");
             continue;
         }
-        cover_up(&mut studio, &bilibili).await?;
+        streamers.push((paths, studio));
+    }
+    validate(
+        archive_pre.as_ref(),
+        streamers.iter().map(|(_, studio)| studio),
+    )?;
+    // 一个稿件上传或投稿失败时继续处理其余稿件
+    let mut failed = 0;
+    for (paths, mut studio) in streamers {
+        if let Err(e) = cover_up(&mut studio, &bilibili).await {
+            error!("稿件 {} 上传封面失败: {e:#}", studio.title);
+            failed += 1;
+            continue;
+        }
 
-        studio.videos = upload(
+        let videos = upload(
             &paths,
             &bilibili,
-            config
-                .line
-                .as_ref()
-                .and_then(|l| UploadLine::from_str(l, true).ok()),
+            line.clone(),
             config.limit,
+            files,
+            max_chunks,
+            resume,
+            rate_limiter.clone(),
+            client.clone(),
         )
-        .await?;
-        bilibili.submit(&studio).await?;
+        .await;
+        studio.videos = match videos {
+            Ok(videos) => videos,
+            Err(e) => {
+                error!("稿件 {} 上传失败: {e:#}", studio.title);
+                failed += 1;
+                continue;
+            }
+        };
+        if let Err(e) = attach_subtitles(&mut studio.videos, &paths, &studio.subtitles) {
+            error!("稿件 {} 未投稿: {e}", studio.title);
+            save_unsubmitted(Some(&studio), &studio.videos);
+            failed += 1;
+            continue;
+        }
+        if let Err(e) = studio.validate_dtime() {
+            error!("稿件 {} 未投稿: {e}", studio.title);
+            save_unsubmitted(Some(&studio), &studio.videos);
+            failed += 1;
+            continue;
+        }
+        let ret = match scheduler.submit(&bilibili, &studio, submit).await {
+            Ok(ret) => ret,
+            Err(e) => {
+                error!("稿件 {} 投稿失败: {e}", studio.title);
+                save_unsubmitted(Some(&studio), &studio.videos);
+                failed += 1;
+                continue;
+            }
+        };
+        if let Err(e) = after_submit(&bilibili, &ret, &studio).await {
+            error!("{e:#}");
+            failed += 1;
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{failed} 个稿件未能完成投稿"));
+    }
+    Ok(())
+}
+
+/// 投稿或追加失败时输出已上传的分P，并把投稿信息保存到当前目录，以便之后不必重新上传
+fn save_unsubmitted(studio: Option<&Studio>, uploaded: &[Video]) {
+    println!("以下分P已上传但未能提交（服务器文件名\t标题）：");
+    for video in uploaded {
+        println!(
+            "{}\t{}",
+            video.filename,
+            video.title.as_deref().unwrap_or_default()
+        );
+    }
+    let Some(studio) = studio else {
+        return;
+    };
+    match submit_scheduler::save_unsubmitted(Path::new("."), studio) {
+        Ok(path) => println!("投稿信息已保存到 {}", path.display()),
+        Err(e) => warn!("保存投稿信息失败: {e}"),
+    }
+}
+
+/// 投稿页的分区列表，获取失败时不检查分区，分区也只能填写 id
+async fn archive_pre(bili: &BiliBili) -> Option<ArchivePre> {
+    match bili.archive_pre_data().await {
+        Ok(archive_pre) => Some(archive_pre),
+        Err(e) => {
+            warn!("获取分区列表失败，跳过分区检查: {e}");
+            None
+        }
+    }
+}
+
+/// 上传前检查投稿信息
+fn validate<'a>(
+    archive_pre: Option<&ArchivePre>,
+    studios: impl IntoIterator<Item = &'a Studio>,
+) -> Result<()> {
+    for studio in studios {
+        studio
+            .validate(archive_pre)
+            .with_context(|| format!("稿件: {}", studio.title))?;
+    }
+    Ok(())
+}
+
+/// 投稿成功后上传分P字幕并加入合集
+async fn after_submit(bili: &BiliBili, ret: &ResponseData, studio: &Studio) -> Result<()> {
+    let has_subtitles = studio
+        .videos
+        .iter()
+        .any(|video| !video.subtitles.is_empty());
+    if !has_subtitles && studio.season_id.is_none() {
+        return Ok(());
+    }
+    let vid = ret
+        .vid()
+        .ok_or_else(|| anyhow!("投稿接口未返回稿件 bv 号: {ret}"))?;
+    bili.upload_subtitles(&vid, &studio.videos)
+        .await
+        .with_context(|| format!("{vid} 投稿成功，但字幕上传失败"))?;
+    if let Some(season_id) = studio.season_id {
+        bili.add_to_season(season_id, &vid)
+            .await
+            .with_context(|| format!("{vid} 投稿成功，但加入合集 {season_id} 失败"))?;
     }
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn append(
     user_cookie: PathBuf,
     vid: Vid,
     video_path: Vec<PathBuf>,
-    line: Option<UploadLine>,
+    line: Option<Line>,
     limit: usize,
+    submit: SubmitOption,
+    subtitles: Vec<SubtitleFile>,
+    client: StatelessClient,
 ) -> Result<()> {
-    let bilibili = login_by_cookies(user_cookie).await?;
-    let mut uploaded_videos = upload(&video_path, &bilibili, line, limit).await?;
-    let mut studio = bilibili.studio_data(&vid).await?;
-    studio.videos.append(&mut uploaded_videos);
-    bilibili.edit(&studio).await?;
+    let bilibili = login_by_cookies(user_cookie, &client).await?;
+    let mut uploaded_videos = upload(
+        &video_path,
+        &bilibili,
+        line,
+        limit,
+        1,
+        None,
+        false,
+        None,
+        client,
+    )
+    .await?;
+    attach_subtitles(&mut uploaded_videos, &video_path, &subtitles)?;
+    let mut studio = bilibili
+        .studio_data(&vid)
+        .await
+        .inspect_err(|_| save_unsubmitted(None, &uploaded_videos))?;
+    studio.videos.extend(uploaded_videos.iter().cloned());
+    SubmitScheduler::default()
+        .edit(&bilibili, &studio, submit)
+        .await
+        .inspect_err(|_| save_unsubmitted(Some(&studio), &uploaded_videos))?;
+    bilibili
+        .upload_subtitles(&vid, &uploaded_videos)
+        .await
+        .with_context(|| format!("{vid} 追加成功，但字幕上传失败"))?;
     // studio.edit(&login_info).await?;
     Ok(())
 }
 
-pub async fn show(user_cookie: PathBuf, vid: Vid) -> Result<()> {
-    let bilibili = login_by_cookies(user_cookie).await?;
-    let video_info = bilibili.video_data(&vid).await?;
-    println!("{}", serde_json::to_string_pretty(&video_info)?);
+pub async fn edit_archive(
+    user_cookie: PathBuf,
+    vid: Vid,
+    args: EditArgs,
+    submit: SubmitOption,
+    yes: bool,
+    client: StatelessClient,
+) -> Result<()> {
+    let config = match &args.config {
+        Some(path) => serde_yaml::from_reader(
+            std::fs::File::open(path).with_context(|| format!("edit config: {path:?}"))?,
+        )?,
+        None => StudioEdit::default(),
+    };
+    let mut edit = config.merge(args.into_edit());
+    let bilibili = login_by_cookies(user_cookie, &client).await?;
+    let mut studio = bilibili.studio_data(&vid).await?;
+    let before = studio.clone();
+    // 网络地址视为已上传的封面
+    if let Some(cover) = edit.cover.as_mut().filter(|c| !c.starts_with("http")) {
+        let input = std::fs::read(Path::new(cover)).with_context(|| format!("cover: {cover}"))?;
+        *cover = bilibili.cover_up(&input).await?;
+    }
+    edit.apply(&mut studio)?;
+
+    let diff = studio_diff(&before, &studio)?;
+    if diff.is_empty() {
+        info!("稿件没有修改");
+        return Ok(());
+    }
+    println!("{}", diff.join("\n"));
+    if !yes
+        && !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("提交以上修改？")
+            .interact()?
+    {
+        return Ok(());
+    }
+    bilibili.edit_with(&studio, submit).await?;
     Ok(())
 }
 
+pub async fn delete(
+    user_cookie: PathBuf,
+    vid: Vid,
+    yes: bool,
+    client: StatelessClient,
+) -> Result<()> {
+    let bilibili = login_by_cookies(user_cookie, &client).await?;
+    let view = bilibili.archive_view(&vid).await?;
+    print!("{view}");
+    if !yes
+        && !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt("删除以上稿件？删除后无法恢复")
+            .default(false)
+            .interact()?
+    {
+        return Ok(());
+    }
+    bilibili.delete_archive(&Vid::Aid(view.archive.aid)).await?;
+    println!("已删除 av{}", view.archive.aid);
+    Ok(())
+}
+
+pub async fn categories(
+    user_cookie: PathBuf,
+    keyword: Option<String>,
+    activities: bool,
+    json: bool,
+    client: StatelessClient,
+) -> Result<()> {
+    let bilibili = login_by_cookies(user_cookie, &client).await?;
+    let archive_pre = bilibili.archive_pre_data().await?;
+    let keyword = keyword.unwrap_or_default();
+    if activities {
+        let activities: Vec<_> = archive_pre
+            .activities
+            .iter()
+            .filter(|activity| activity.name.contains(&keyword))
+            .collect();
+        if json {
+            println!("{}", serde_json::to_string_pretty(&activities)?);
+            return Ok(());
+        }
+        for activity in activities {
+            println!(
+                "{}\t{}\t话题 {}",
+                activity.id, activity.name, activity.topic_id
+            );
+        }
+        return Ok(());
+    }
+    let categories: Vec<_> = archive_pre.search(&keyword).collect();
+    if json {
+        println!("{}", serde_json::to_string_pretty(&categories)?);
+        return Ok(());
+    }
+    print_categories(&archive_pre, &categories);
+    Ok(())
+}
+
+/// 按一级分区分组打印
+fn print_categories(archive_pre: &ArchivePre, categories: &[&Category]) {
+    let mut parent = None;
+    for category in categories {
+        if parent != Some(category.parent) {
+            parent = Some(category.parent);
+            println!(
+                "{} ({})",
+                archive_pre.parent_name(category),
+                category.parent
+            );
+        }
+        println!("  {}\t{}\t{}", category.id, category.name, category.desc);
+    }
+}
+
+pub async fn show(
+    user_cookie: PathBuf,
+    vid: Vid,
+    json: bool,
+    client: StatelessClient,
+) -> Result<()> {
+    let bilibili = login_by_cookies(user_cookie, &client).await?;
+    if json {
+        let video_info = bilibili.video_data(&vid).await?;
+        println!("{}", serde_json::to_string_pretty(&video_info)?);
+    } else {
+        print!("{}", bilibili.archive_view(&vid).await?);
+    }
+    Ok(())
+}
+
+pub async fn watch(
+    user_cookie: PathBuf,
+    vid: Vid,
+    options: WatchOptions,
+    client: StatelessClient,
+) -> Result<ReviewState> {
+    let bilibili = login_by_cookies(user_cookie, &client).await?;
+    let state = bilibili
+        .watch(&vid, &options, |state, view| {
+            println!("{}\t{}\t{state}", view.archive.bvid, view.archive.title)
+        })
+        .await?;
+    if !state.is_final() {
+        println!("等待超时，当前状态: {state}");
+    }
+    Ok(state)
+}
+
 pub async fn list(
     user_cookie: PathBuf,
     is_pubing: bool,
     pubed: bool,
     not_pubed: bool,
+    client: StatelessClient,
 ) -> Result<()> {
     let status = match (is_pubing, pubed, not_pubed) {
         (true, false, false) => "is_pubing",
@@ -168,7 +515,7 @@ pub async fn list(
         }
     };
 
-    let bilibili = login_by_cookies(user_cookie).await?;
+    let bilibili = login_by_cookies(user_cookie, &client).await?;
     bilibili
         .all_archives(status)
         .await?
@@ -177,23 +524,151 @@ pub async fn list(
     Ok(())
 }
 
-async fn login_by_cookies(user_cookie: PathBuf) -> Result<BiliBili> {
-    let result = credential::login_by_cookies(&user_cookie).await;
-    Ok(if let Err(Kind::IO(_)) = result {
-        result
-            .with_context(|| String::from("open cookies file: ") + &user_cookie.to_string_lossy())?
-    } else {
-        let bili = result?;
-        info!(
-            "user: {}",
-            bili.my_info().await?["data"]["name"]
-                .as_str()
-                .unwrap_or_default()
-        );
-        bili
+/// 按名称查找上传线路，`lines` 中的自定义线路优先于内置线路
+pub fn find_line(name: &str, lines: &HashMap<String, Line>) -> Result<Line> {
+    line::by_name(name, lines).ok_or_else(|| {
+        anyhow!(
+            "unknown upload line {name}, expected one of {} or a line defined in lines",
+            line::BUILTIN_LINES.join(", ")
+        )
     })
 }
 
+async fn login_by_cookies(user_cookie: PathBuf, client: &StatelessClient) -> Result<BiliBili> {
+    let result = credential::login_by_cookies_with(&user_cookie, client.into()).await;
+    Ok(match result {
+        Err(Kind::IO(_)) => result.with_context(|| {
+            String::from("open cookies file: ") + &user_cookie.to_string_lossy()
+        })?,
+        Err(Kind::BiliApi(e)) if e.needs_relogin() => {
+            return Err(anyhow!(e).context(format!(
+                "登录信息已失效，请通过 biliup renew 刷新或 biliup login 重新登录: {}",
+                user_cookie.display()
+            )))
+        }
+        result => {
+            let bili = result?;
+            info!(
+                "user: {}",
+                bili.my_info().await?["data"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+            );
+            bili
+        }
+    })
+}
+
+pub async fn season(
+    user_cookie: PathBuf,
+    command: SeasonCommands,
+    client: StatelessClient,
+) -> Result<()> {
+    let bili = login_by_cookies(user_cookie, &client).await?;
+    match command {
+        SeasonCommands::List => {
+            for season in bili.seasons().await? {
+                println!("{}\t{}", season.id, season.title);
+                for section in &season.sections {
+                    println!(
+                        "  小节 {}\t{}\t{}集",
+                        section.id, section.title, section.ep_count
+                    );
+                }
+            }
+        }
+        SeasonCommands::Episodes { section_id } => {
+            for episode in bili.section_episodes(section_id).await? {
+                println!(
+                    "{}\tav{}\t{}\t{}",
+                    episode.id, episode.aid, episode.bvid, episode.title
+                );
+            }
+        }
+        SeasonCommands::Create { title, desc, cover } => {
+            let cover = match cover {
+                Some(cover) if !cover.starts_with("http") => {
+                    let input = std::fs::read(Path::new(&cover))
+                        .with_context(|| format!("cover: {cover}"))?;
+                    bili.cover_up(&input).await?
+                }
+                cover => cover.unwrap_or_default(),
+            };
+            let season_id = bili.create_season(&title, &desc, &cover).await?;
+            println!("{season_id}");
+        }
+        SeasonCommands::Add {
+            season_id,
+            vids,
+            section,
+        } => {
+            let section_id = match section {
+                Some(section_id) => section_id,
+                None => find_season(&bili, season_id)
+                    .await?
+                    .sections
+                    .first()
+                    .map(|section| section.id)
+                    .ok_or_else(|| anyhow!("合集 {season_id} 没有小节"))?,
+            };
+            for vid in vids {
+                bili.add_to_section(section_id, &vid).await?;
+                println!("已加入合集: {vid}");
+            }
+        }
+        SeasonCommands::Remove { season_id, vids } => {
+            let season = find_season(&bili, season_id).await?;
+            let mut episodes = Vec::new();
+            for section in &season.sections {
+                episodes.extend(bili.section_episodes(section.id).await?);
+            }
+            for vid in vids {
+                let episode = episodes
+                    .iter()
+                    .find(|episode| episode.is(&vid))
+                    .ok_or_else(|| anyhow!("{vid} 不在合集 {season_id} 中"))?;
+                bili.remove_episode(episode.id).await?;
+                println!("已移出合集: {vid}");
+            }
+        }
+        SeasonCommands::Order { section_id, vids } => {
+            let section = bili
+                .seasons()
+                .await?
+                .into_iter()
+                .flat_map(|season| season.sections)
+                .find(|section| section.id == section_id)
+                .ok_or_else(|| anyhow!("未找到小节 {section_id}"))?;
+            let mut episodes = bili.section_episodes(section_id).await?;
+            episodes.sort_by_key(|episode| episode.order);
+            let mut ids = Vec::new();
+            for vid in &vids {
+                let episode = episodes
+                    .iter()
+                    .find(|episode| episode.is(vid))
+                    .ok_or_else(|| anyhow!("{vid} 不在小节 {section_id} 中"))?;
+                ids.push(episode.id);
+            }
+            let rest: Vec<_> = episodes
+                .iter()
+                .map(|episode| episode.id)
+                .filter(|id| !ids.contains(id))
+                .collect();
+            ids.extend(rest);
+            bili.sort_section(&section, &ids).await?;
+        }
+    }
+    Ok(())
+}
+
+async fn find_season(bili: &BiliBili, season_id: u32) -> Result<Season> {
+    bili.seasons()
+        .await?
+        .into_iter()
+        .find(|season| season.id == season_id)
+        .ok_or_else(|| anyhow!("未找到合集 {season_id}"))
+}
+
 pub async fn cover_up(studio: &mut Studio, bili: &BiliBili) -> Result<()> {
     if !studio.cover.is_empty() {
         let url = bili
@@ -208,66 +683,122 @@ pub async fn cover_up(studio: &mut Studio, bili: &BiliBili) -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn upload(
     video_path: &[PathBuf],
     bili: &BiliBili,
-    line: Option<UploadLine>,
+    line: Option<Line>,
     limit: usize,
+    files: usize,
+    max_chunks: Option<usize>,
+    resume: bool,
+    rate_limiter: Option<RateLimiter>,
+    client: StatelessClient,
 ) -> Result<Vec<Video>> {
-    info!("number of concurrent futures: {limit}");
-    let mut videos = Vec::new();
-    let client = StatelessClient::default();
-    let line = match line {
-        Some(UploadLine::Bda2) => line::bda2(),
-        Some(UploadLine::Ws) => line::ws(),
-        Some(UploadLine::Qn) => line::qn(),
-        // Some(UploadLine::Kodo) => line::kodo(),
-        // Some(UploadLine::Cos) => line::cos(),
-        // Some(UploadLine::CosInternal) => line::cos_internal(),
-        Some(UploadLine::Bldsa) => line::bldsa(),
-        Some(UploadLine::Tx) => line::tx(),
-        Some(UploadLine::Txa) => line::txa(),
-        Some(UploadLine::Bda) => line::bda(),
-        Some(UploadLine::Alia) => line::alia(),
-        None => Probe::probe(&client.client).await.unwrap_or_default(),
+    let files = files.max(1);
+    let chunk_limiter = ChunkLimiter::new(max_chunks.unwrap_or(limit * files));
+    info!(
+        "number of concurrent futures: {limit}, files: {files}, max chunks: {}",
+        chunk_limiter.max_chunks()
+    );
+    if let Some(rate_limiter) = &rate_limiter {
+        info!("max upload rate: {} B/s", rate_limiter.bytes_per_sec());
+    }
+    let line: Failover = match line {
+        Some(line) => line.into(),
+        None => Failover::new(
+            Probe::rank_cached(&client.client, &Probe::cache_file(), PROBE_CACHE_TTL)
+                .await
+                .unwrap_or_default(),
+        ),
     };
+    let line = line.resume(resume);
     // let line = line::kodo();
-    for video_path in video_path {
-        info!("{line:?}");
-        let video_file = VideoFile::new(video_path)
-            .with_context(|| format!("file {}", video_path.to_string_lossy()))?;
-        let total_size = video_file.total_size;
-        let file_name = video_file.file_name.clone();
-        let uploader = line.pre_upload(bili, video_file).await?;
-        //Progress bar
-        let pb = ProgressBar::new(total_size);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?);
-        // pb.enable_steady_tick(Duration::from_secs(1));
-        // pb.tick()
-
-        let instant = Instant::now();
-
-        let video = uploader
-            .upload(client.clone(), limit, |vs| {
-                vs.map(|chunk| {
-                    let pb = pb.clone();
-                    let chunk = chunk?;
-                    let len = chunk.len();
-                    Ok((Progressbar::new(chunk, pb), len))
-                })
-            })
-            .await?;
-        pb.finish_and_clear();
-        let t = instant.elapsed().as_millis();
-        info!(
-            "Upload completed: {file_name} => cost {:.2}s, {:.2} MB/s.",
-            t as f64 / 1000.,
-            total_size as f64 / 1000. / t as f64
-        );
-        videos.push(video);
-    }
-    Ok(videos)
+    let multi = MultiProgress::new();
+    // buffered 按输入顺序返回结果，保证分P顺序不变
+    futures::stream::iter(video_path)
+        .map(|video_path| {
+            let line = &line;
+            let client = &client;
+            let multi = &multi;
+            let chunk_limiter = &chunk_limiter;
+            let rate_limiter = &rate_limiter;
+            async move {
+                info!("{:?}", line.lines());
+                let video_file = open_video(&client.client, video_path)
+                    .await
+                    .with_context(|| format!("file {}", video_path.to_string_lossy()))?;
+                let total_size = video_file.size();
+                let file_name = video_file.file_name.clone();
+                //Progress bar
+                let pb = match total_size {
+                    Some(total_size) => {
+                        let pb = multi.add(ProgressBar::new(total_size));
+                        pb.set_style(ProgressStyle::default_bar()
+                            .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")?);
+                        pb
+                    }
+                    None => {
+                        let pb = multi.add(ProgressBar::new_spinner());
+                        pb.set_style(ProgressStyle::default_spinner()
+                            .template("{spinner:.green} [{elapsed_precise}] {bytes} ({bytes_per_sec})")?);
+                        pb
+                    }
+                };
+                // pb.enable_steady_tick(Duration::from_secs(1));
+                // pb.tick()
+
+                let instant = Instant::now();
+
+                let video = line
+                    .upload(
+                        bili,
+                        client.clone(),
+                        video_file,
+                        limit,
+                        rate_limiter.clone(),
+                        Some(chunk_limiter),
+                        &|event| match event {
+                            // 切换线路后从头开始计算进度
+                            UploadEvent::PreUpload { .. } => pb.set_position(0),
+                            UploadEvent::ChunkFinished { size, .. } => pb.inc(size as u64),
+                            UploadEvent::ChunkRetried {
+                                index,
+                                attempt,
+                                error,
+                                ..
+                            } => pb.println(format!(
+                                "{file_name}: chunk {index} retry #{attempt}: {error}"
+                            )),
+                            _ => {}
+                        },
+                    )
+                    .await?;
+                pb.finish_and_clear();
+                let t = instant.elapsed().as_millis();
+                info!(
+                    "Upload completed: {file_name} => cost {:.2}s, {:.2} MB/s, md5: {}.",
+                    t as f64 / 1000.,
+                    total_size.unwrap_or(pb.position()) as f64 / 1000. / t as f64,
+                    video.md5.as_deref().unwrap_or_default()
+                );
+                Ok::<_, anyhow::Error>(video)
+            }
+        })
+        .buffered(files)
+        .try_collect()
+        .await
+}
+
+/// `-` 表示标准输入，http(s) 地址直接下载上传，其余为本地文件
+async fn open_video(client: &reqwest::Client, video_path: &Path) -> Result<VideoFile> {
+    Ok(match video_path.to_str() {
+        Some("-") => VideoFile::from_source(VideoFile::STDIN_FILE_NAME, tokio::io::stdin()),
+        Some(url) if url.starts_with("http://") || url.starts_with("https://") => {
+            VideoFile::from_url(client, url).await?
+        }
+        _ => VideoFile::new(video_path)?,
+    })
 }
 
 pub async fn login_by_password(credential: Credential) -> Result<LoginInfo> {
@@ -370,12 +901,6 @@ pub async fn login_by_webqr_cookies(credential: Credential) -> Result<LoginInfo>
         .await?)
 }
 
-impl From<Progressbar> for Body {
-    fn from(async_stream: Progressbar) -> Self {
-        Body::wrap_stream(async_stream)
-    }
-}
-
 #[inline]
 pub fn fopen_rw<P: AsRef<Path>>(path: P) -> Result<std::fs::File> {
     let path = path.as_ref();
@@ -385,49 +910,3 @@ pub fn fopen_rw<P: AsRef<Path>>(path: P) -> Result<std::fs::File> {
         .open(path)
         .with_context(|| String::from("open cookies file: ") + &path.to_string_lossy())
 }
-
-#[derive(Clone)]
-struct Progressbar {
-    bytes: Bytes,
-    pb: ProgressBar,
-}
-
-impl Progressbar {
-    pub fn new(bytes: Bytes, pb: ProgressBar) -> Self {
-        Self { bytes, pb }
-    }
-
-    pub fn progress(&mut self) -> Result<Option<Bytes>> {
-        let pb = &self.pb;
-
-        let content_bytes = &mut self.bytes;
-
-        let n = content_bytes.remaining();
-
-        let pc = 4096;
-        if n == 0 {
-            Ok(None)
-        } else if n < pc {
-            pb.inc(n as u64);
-            Ok(Some(content_bytes.copy_to_bytes(n)))
-        } else {
-            pb.inc(pc as u64);
-
-            Ok(Some(content_bytes.copy_to_bytes(pc)))
-        }
-    }
-}
-
-impl Stream for Progressbar {
-    type Item = Result<Bytes>;
-
-    fn poll_next(
-        mut self: Pin<&mut Self>,
-        _cx: &mut std::task::Context<'_>,
-    ) -> Poll<Option<Self::Item>> {
-        match self.progress()? {
-            None => Poll::Ready(None),
-            Some(s) => Poll::Ready(Some(Ok(s))),
-        }
-    }
-}
@@ -15,6 +15,7 @@ use biliup::downloader::util::Segmentable;
 use tracing_subscriber::layer::SubscriberExt;
 use biliup::credential::Credential;
 use biliup::downloader::extractor::CallbackFn;
+use reqwest::Proxy;
 
 #[derive(FromPyObject)]
 pub enum PySegment {
@@ -35,8 +36,9 @@ fn download(
     header_map: HashMap<String, String>,
     file_name: &str,
     segment: PySegment,
+    proxy: Option<&str>,
 ) -> PyResult<()> {
-    download_with_callback(py, url, header_map, file_name, segment, None)
+    download_with_callback(py, url, header_map, file_name, segment, None, proxy)
 }
 
 #[pyfunction]
@@ -47,7 +49,9 @@ fn download_with_callback(
     file_name: &str,
     segment: PySegment,
     file_name_callback_fn: Option<PyObject>,
+    proxy: Option<&str>,
 ) -> PyResult<()> {
+    let proxy = parse_proxy(proxy)?;
     py.allow_threads(|| {
         let map = construct_headers(header_map);
         // 输出到控制台中
@@ -88,7 +92,7 @@ fn download_with_callback(
 
         let collector = formatting_layer.with(file_layer);
         tracing::subscriber::with_default(collector, || -> PyResult<()> {
-            match biliup::downloader::download(url, map, file_name, segment, file_name_hook) {
+            match biliup::downloader::download(url, map, file_name, segment, file_name_hook, proxy) {
                 Ok(res) => Ok(res),
                 Err(err) => Err(pyo3::exceptions::PyRuntimeError::new_err(format!(
                     "{}, {}",
@@ -100,6 +104,13 @@ fn download_with_callback(
     })
 }
 
+fn parse_proxy(proxy: Option<&str>) -> PyResult<Option<Proxy>> {
+    proxy
+        .map(Proxy::all)
+        .transpose()
+        .map_err(|err| pyo3::exceptions::PyValueError::new_err(format!("proxy: {err}")))
+}
+
 #[pyfunction]
 fn login_by_cookies(file: String) -> PyResult<bool> {
     let rt = tokio::runtime::Runtime::new().unwrap();
@@ -212,7 +223,10 @@ fn upload(
     desc_v2: Vec<PyCredit>,
     dtime: Option<u32>,
     line: Option<UploadLine>,
+    progress_callback: Option<PyObject>,
+    proxy: Option<&str>,
 ) -> PyResult<()> {
+    let proxy = parse_proxy(proxy)?;
     py.allow_threads(|| {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
@@ -258,6 +272,8 @@ fn upload(
                 .no_reprint(no_reprint)
                 .open_elec(open_elec)
                 .desc_v2_credit(desc_v2)
+                .progress_callback(progress_callback)
+                .proxy(proxy)
                 .build();
 
             match rt.block_on(uploader::upload(studio_pre)) {
@@ -275,7 +291,7 @@ fn upload(
 
 #[allow(clippy::too_many_arguments)]
 #[pyfunction]
-#[pyo3(signature = (video_path, cookie_file, title, tid=171, tag="".to_string(), copyright=2, source="".to_string(), desc="".to_string(), dynamic="".to_string(), cover="".to_string(), dolby=0, lossless_music=0, no_reprint=0, open_elec=0, up_close_reply=false, up_selection_reply=false, up_close_danmu=false, limit=3, desc_v2=vec![], dtime=None, line=None))]
+#[pyo3(signature = (video_path, cookie_file, title, tid=171, tag="".to_string(), copyright=2, source="".to_string(), desc="".to_string(), dynamic="".to_string(), cover="".to_string(), dolby=0, lossless_music=0, no_reprint=0, open_elec=0, up_close_reply=false, up_selection_reply=false, up_close_danmu=false, limit=3, desc_v2=vec![], dtime=None, line=None, progress_callback=None, proxy=None))]
 fn upload_by_app(
     py: Python<'_>,
     video_path: Vec<PathBuf>,
@@ -299,7 +315,10 @@ fn upload_by_app(
     desc_v2: Vec<PyCredit>,
     dtime: Option<u32>,
     line: Option<UploadLine>,
+    progress_callback: Option<PyObject>,
+    proxy: Option<&str>,
 ) -> PyResult<()> {
+    let proxy = parse_proxy(proxy)?;
     py.allow_threads(|| {
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
@@ -348,6 +367,8 @@ fn upload_by_app(
                 .up_selection_reply(up_selection_reply)
                 .up_close_danmu(up_close_danmu)
                 .desc_v2_credit(desc_v2)
+                .progress_callback(progress_callback)
+                .proxy(proxy)
                 .build();
 
             match rt.block_on(uploader::upload_by_app(studio_pre)) {
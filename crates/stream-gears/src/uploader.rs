@@ -2,12 +2,14 @@ use anyhow::{Context, Result};
 use biliup::client::StatelessClient;
 use biliup::error::Kind;
 use biliup::uploader::bilibili::{Credit, ResponseData, Studio};
-use biliup::uploader::credential::login_by_cookies;
-use biliup::uploader::line::Probe;
+use biliup::uploader::credential::login_by_cookies_with_proxy;
+use biliup::uploader::line::{Failover, Probe, PROBE_CACHE_TTL};
+use biliup::uploader::observer::{UploadEvent, UploadObserver};
 use biliup::uploader::{line, VideoFile};
-use futures::StreamExt;
 use pyo3::prelude::*;
 use pyo3::pyclass;
+use reqwest::header::HeaderMap;
+use reqwest::Proxy;
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -22,14 +24,14 @@ pub enum UploadLine {
     Bda2,
     Ws,
     Qn,
-    // Kodo,
-    // Cos,
-    // CosInternal,
     Bldsa,
     Tx,
     Txa,
     Bda,
     Alia,
+    Kodo,
+    Cos,
+    CosInternal,
 }
 
 #[derive(FromPyObject)]
@@ -70,6 +72,34 @@ pub struct StudioPre {
     desc_v2_credit: Vec<PyCredit>,
     #[builder(default)]
     extra_fields: Option<HashMap<String, serde_json::Value>>,
+    #[builder(default)]
+    progress_callback: Option<PyObject>,
+    /// 登录、投稿与上传使用的代理
+    #[builder(default)]
+    proxy: Option<Proxy>,
+}
+
+/// 把上传事件转为 dict 交给 Python 回调
+struct PyObserver(Option<PyObject>);
+
+impl UploadObserver for PyObserver {
+    fn on_event(&self, event: UploadEvent) {
+        let Some(callback) = &self.0 else {
+            return;
+        };
+        let Ok(event) = serde_json::to_string(&event) else {
+            return;
+        };
+        Python::with_gil(|py| {
+            let result = py
+                .import_bound("json")
+                .and_then(|json| json.call_method1("loads", (event,)))
+                .and_then(|event| callback.call1(py, (event,)));
+            if let Err(e) = result {
+                tracing::error!("Unable to invoke the progress callback: {e}");
+            }
+        })
+    }
 }
 
 pub async fn upload(studio_pre: StudioPre) -> Result<ResponseData> {
@@ -97,10 +127,12 @@ pub async fn upload(studio_pre: StudioPre) -> Result<ResponseData> {
         open_elec,
         desc_v2_credit,
         extra_fields,
+        progress_callback,
+        proxy,
         ..
     } = studio_pre;
 
-    let bilibili = login_by_cookies(&cookie_file).await;
+    let bilibili = login_by_cookies_with_proxy(&cookie_file, proxy.clone()).await;
     let bilibili = if let Err(Kind::IO(_)) = bilibili {
         bilibili
             .with_context(|| String::from("open cookies file: ") + &cookie_file.to_string_lossy())?
@@ -108,46 +140,53 @@ pub async fn upload(studio_pre: StudioPre) -> Result<ResponseData> {
         bilibili?
     };
 
-    let client = StatelessClient::default();
+    let client = StatelessClient::new(HeaderMap::new(), proxy);
+    let observer = PyObserver(progress_callback);
     let mut videos = Vec::new();
-    let line = match line {
-        Some(UploadLine::Bda2) => line::bda2(),
-        Some(UploadLine::Ws) => line::ws(),
-        Some(UploadLine::Qn) => line::qn(),
-        // Some(UploadLine::Kodo) => line::kodo(),
-        // Some(UploadLine::Cos) => line::cos(),
-        // Some(UploadLine::CosInternal) => line::cos_internal(),
-        Some(UploadLine::Bda) => line::bda(),
-        Some(UploadLine::Tx) => line::tx(),
-        Some(UploadLine::Txa) => line::txa(),
-        Some(UploadLine::Bldsa) => line::bldsa(),
-        Some(UploadLine::Alia) => line::alia(),
-        None => Probe::probe(&client.client).await.unwrap_or_default(),
+    let line: Failover = match line {
+        Some(UploadLine::Bda2) => line::bda2().into(),
+        Some(UploadLine::Ws) => line::ws().into(),
+        Some(UploadLine::Qn) => line::qn().into(),
+        Some(UploadLine::Kodo) => line::kodo().into(),
+        Some(UploadLine::Cos) => line::cos().into(),
+        Some(UploadLine::CosInternal) => line::cos_internal().into(),
+        Some(UploadLine::Bda) => line::bda().into(),
+        Some(UploadLine::Tx) => line::tx().into(),
+        Some(UploadLine::Txa) => line::txa().into(),
+        Some(UploadLine::Bldsa) => line::bldsa().into(),
+        Some(UploadLine::Alia) => line::alia().into(),
+        None => Failover::new(
+            Probe::rank_cached(&client.client, &Probe::cache_file(), PROBE_CACHE_TTL)
+                .await
+                .unwrap_or_default(),
+        ),
     };
     for video_path in video_path {
         println!("{:?}", video_path.canonicalize()?.to_str());
-        info!("{line:?}");
+        info!("{:?}", line.lines());
         let video_file = VideoFile::new(&video_path)?;
         let total_size = video_file.total_size;
         let file_name = video_file.file_name.clone();
-        let uploader = line.pre_upload(&bilibili, video_file).await?;
 
         let instant = Instant::now();
 
-        let video = uploader
-            .upload(client.clone(), limit, |vs| {
-                vs.map(|vs| {
-                    let chunk = vs?;
-                    let len = chunk.len();
-                    Ok((chunk, len))
-                })
-            })
+        let video = line
+            .upload(
+                &bilibili,
+                client.clone(),
+                video_file,
+                limit,
+                None,
+                None,
+                &observer,
+            )
             .await?;
         let t = instant.elapsed().as_millis();
         info!(
-            "Upload completed: {file_name} => cost {:.2}s, {:.2} MB/s.",
+            "Upload completed: {file_name} => cost {:.2}s, {:.2} MB/s, md5: {}.",
             t as f64 / 1000.,
-            total_size as f64 / 1000. / t as f64
+            total_size as f64 / 1000. / t as f64,
+            video.md5.as_deref().unwrap_or_default()
         );
         videos.push(video);
     }
@@ -191,7 +230,9 @@ pub async fn upload(studio_pre: StudioPre) -> Result<ResponseData> {
         studio.cover = url;
     }
 
-    Ok(bilibili.submit(&studio).await?)
+    let result = bilibili.submit(&studio).await;
+    observer.on_event(UploadEvent::submitted(&studio.title, &result));
+    Ok(result?)
 }
 
 pub async fn upload_by_app(studio_pre: StudioPre) -> Result<ResponseData> {
@@ -222,9 +263,11 @@ pub async fn upload_by_app(studio_pre: StudioPre) -> Result<ResponseData> {
         up_close_danmu,
         desc_v2_credit,
         extra_fields,
+        progress_callback,
+        proxy,
     } = studio_pre;
 
-    let bilibili = login_by_cookies(&cookie_file).await;
+    let bilibili = login_by_cookies_with_proxy(&cookie_file, proxy.clone()).await;
     let bilibili = if let Err(Kind::IO(_)) = bilibili {
         bilibili
             .with_context(|| String::from("open cookies file: ") + &cookie_file.to_string_lossy())?
@@ -232,46 +275,53 @@ pub async fn upload_by_app(studio_pre: StudioPre) -> Result<ResponseData> {
         bilibili?
     };
 
-    let client = StatelessClient::default();
+    let client = StatelessClient::new(HeaderMap::new(), proxy);
+    let observer = PyObserver(progress_callback);
     let mut videos = Vec::new();
-    let line = match line {
-        Some(UploadLine::Bda2) => line::bda2(),
-        Some(UploadLine::Ws) => line::ws(),
-        Some(UploadLine::Qn) => line::qn(),
-        // Some(UploadLine::Kodo) => line::kodo(),
-        // Some(UploadLine::Cos) => line::cos(),
-        // Some(UploadLine::CosInternal) => line::cos_internal(),
-        Some(UploadLine::Bda) => line::bda(),
-        Some(UploadLine::Tx) => line::tx(),
-        Some(UploadLine::Txa) => line::txa(),
-        Some(UploadLine::Bldsa) => line::bldsa(),
-        Some(UploadLine::Alia) => line::alia(),
-        None => Probe::probe(&client.client).await.unwrap_or_default(),
+    let line: Failover = match line {
+        Some(UploadLine::Bda2) => line::bda2().into(),
+        Some(UploadLine::Ws) => line::ws().into(),
+        Some(UploadLine::Qn) => line::qn().into(),
+        Some(UploadLine::Kodo) => line::kodo().into(),
+        Some(UploadLine::Cos) => line::cos().into(),
+        Some(UploadLine::CosInternal) => line::cos_internal().into(),
+        Some(UploadLine::Bda) => line::bda().into(),
+        Some(UploadLine::Tx) => line::tx().into(),
+        Some(UploadLine::Txa) => line::txa().into(),
+        Some(UploadLine::Bldsa) => line::bldsa().into(),
+        Some(UploadLine::Alia) => line::alia().into(),
+        None => Failover::new(
+            Probe::rank_cached(&client.client, &Probe::cache_file(), PROBE_CACHE_TTL)
+                .await
+                .unwrap_or_default(),
+        ),
     };
     for video_path in video_path {
         println!("{:?}", video_path.canonicalize()?.to_str());
-        info!("{line:?}");
+        info!("{:?}", line.lines());
         let video_file = VideoFile::new(&video_path)?;
         let total_size = video_file.total_size;
         let file_name = video_file.file_name.clone();
-        let uploader = line.pre_upload(&bilibili, video_file).await?;
 
         let instant = Instant::now();
 
-        let video = uploader
-            .upload(client.clone(), limit, |vs| {
-                vs.map(|vs| {
-                    let chunk = vs?;
-                    let len = chunk.len();
-                    Ok((chunk, len))
-                })
-            })
+        let video = line
+            .upload(
+                &bilibili,
+                client.clone(),
+                video_file,
+                limit,
+                None,
+                None,
+                &observer,
+            )
             .await?;
         let t = instant.elapsed().as_millis();
         info!(
-            "Upload completed: {file_name} => cost {:.2}s, {:.2} MB/s.",
+            "Upload completed: {file_name} => cost {:.2}s, {:.2} MB/s, md5: {}.",
             t as f64 / 1000.,
-            total_size as f64 / 1000. / t as f64
+            total_size as f64 / 1000. / t as f64,
+            video.md5.as_deref().unwrap_or_default()
         );
         videos.push(video);
     }
@@ -318,5 +368,7 @@ pub async fn upload_by_app(studio_pre: StudioPre) -> Result<ResponseData> {
         studio.cover = url;
     }
 
-    Ok(bilibili.submit_by_app(&studio).await?)
+    let result = bilibili.submit_by_app(&studio).await;
+    observer.on_event(UploadEvent::submitted(&studio.title, &result));
+    Ok(result?)
 }
@@ -1,4 +1,4 @@
-from typing import Dict, List, Optional, Callable
+from typing import Any, Dict, List, Optional, Callable
 from enum import Enum
 
 from .pyobject import Segment, Credit
@@ -7,7 +7,8 @@ from .pyobject import Segment, Credit
 def download(url: str,
              header_map: Dict[str, str],
              file_name: str,
-             segment: Segment) -> None:
+             segment: Segment,
+             proxy: Optional[str] = None) -> None:
     """
     下载视频
 
@@ -15,6 +16,7 @@ def download(url: str,
     :param Dict[str, str] header_map: HTTP请求头
     :param str file_name: 文件名格式
     :param Segment segment: 视频分段设置
+    :param Optional[str] proxy: 下载使用的代理, 如 socks5h://127.0.0.1:1080
     """
 
 
@@ -22,7 +24,8 @@ def download_with_callback(url: str,
                header_map: Dict[str, str],
                file_name: str,
                segment: Segment,
-               file_name_callback_fn: Callable[[str], None]) -> None:
+               file_name_callback_fn: Callable[[str], None],
+               proxy: Optional[str] = None) -> None:
     """
     下载视频
 
@@ -31,6 +34,7 @@ def download_with_callback(url: str,
     :param str file_name: 文件名格式
     :param Segment segment: 视频分段设置
     :param Callable[[str], None] file_name_callback_fn: 回调已下载完成文件名
+    :param Optional[str] proxy: 下载使用的代理, 如 socks5h://127.0.0.1:1080
     """
 
 
@@ -111,15 +115,6 @@ class UploadLine(Enum):
     Qn = 3
     """七牛upos"""
 
-    # Kodo = 4
-    # """七牛bupfetch"""
-
-    # Cos = 5
-    # """腾讯bupfetch"""
-
-    # CosInternal = 6
-    # """上海腾讯云内网"""
-
     Bda = 4
     """百度云海外"""
 
@@ -135,6 +130,15 @@ class UploadLine(Enum):
     Alia = 8
     """阿里云upos"""
 
+    Kodo = 9
+    """七牛bupfetch"""
+
+    Cos = 10
+    """腾讯bupfetch"""
+
+    CosInternal = 11
+    """上海腾讯云内网"""
+
 
 def upload(video_path: List[str],
            cookie_file: str,
@@ -153,7 +157,9 @@ def upload(video_path: List[str],
            limit: int,
            desc_v2: List[Credit],
            dtime: Optional[int],
-           line: Optional[UploadLine]) -> None:
+           line: Optional[UploadLine],
+           progress_callback: Optional[Callable[[Dict[str, Any]], None]] = None,
+           proxy: Optional[str] = None) -> None:
     """
     上传视频稿件
 
@@ -175,6 +181,9 @@ def upload(video_path: List[str],
     :param List[Credit] desc_v2: 视频简介v2
     :param Optional[dtime] int dtime: 定时发布时间, 距离提交大于2小时小于15天, 格式为10位时间戳
     :param Optional[UploadLine] line: 上传线路
+    :param Optional[Callable[[Dict[str, Any]], None]] progress_callback: 上传事件回调, 事件的type字段为
+        pre_upload、chunk_started、chunk_finished、chunk_retried、file_completed或submitted, 文件事件的path字段为完整路径
+    :param Optional[str] proxy: 登录、投稿与上传使用的代理, 如 socks5h://127.0.0.1:1080
     """
 
 def upload_by_app(video_path: List[str],
@@ -197,7 +206,9 @@ def upload_by_app(video_path: List[str],
            limit: int,
            desc_v2: List[Credit],
            dtime: Optional[int],
-           line: Optional[UploadLine]) -> None:
+           line: Optional[UploadLine],
+           progress_callback: Optional[Callable[[Dict[str, Any]], None]] = None,
+           proxy: Optional[str] = None) -> None:
     """
     上传视频稿件
 
@@ -222,4 +233,7 @@ def upload_by_app(video_path: List[str],
     :param List[Credit] desc_v2: 视频简介v2
     :param Optional[dtime] int dtime: 定时发布时间, 距离提交大于2小时小于15天, 格式为10位时间戳
     :param Optional[UploadLine] line: 上传线路
+    :param Optional[Callable[[Dict[str, Any]], None]] progress_callback: 上传事件回调, 事件的type字段为
+        pre_upload、chunk_started、chunk_finished、chunk_retried、file_completed或submitted, 文件事件的path字段为完整路径
+    :param Optional[str] proxy: 登录、投稿与上传使用的代理, 如 socks5h://127.0.0.1:1080
     """
\ No newline at end of file